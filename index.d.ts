@@ -12,14 +12,459 @@ export const enum TemplateType {
   Project = 2
 }
 /**
- * 初始化本地yaml配置文件
+ * 目标目录已存在时的处理方式
 */
-export function initYamlFile(): void
+export const enum ConflictStrategy {
+  /** 跳过已存在的模板 */
+  Skip = 0,
+  /** 直接覆盖同名文件 */
+  Overwrite = 1,
+  /** 先把已存在的目录重命名为 .bak 备份，再重新生成 */
+  Backup = 2,
+  /** 生成到带数字后缀的新目录 */
+  Rename = 3,
+  /** 逐个文件询问是否覆盖 */
+  Prompt = 4,
+  /** 与本地文件合并，冲突处写入冲突标记 */
+  Merge = 5
+}
+/**
+ * dry run 时生成计划的输出格式
+*/
+export const enum PlanFormat {
+  Tree = 0,
+  Json = 1
+}
+/**
+ * 生成模板时的可选参数
+*/
+export interface GenerateOptions {
+  /**
+   * 目标目录已存在时的处理方式，默认跳过
+  */
+  conflictStrategy?: ConflictStrategy
+  /**
+   * 只解析远程模板并输出生成计划，不写入任何文件
+  */
+  dryRun?: boolean
+  /**
+   * dry run 计划的输出格式，默认 Tree
+  */
+  planFormat?: PlanFormat
+  /**
+   * 生成目录，优先于 wego.yaml 中的 destinations，支持 {name}、{template}、{type} 占位符
+  */
+  dest?: string
+  /**
+   * 要安装的功能组或可选依赖，设置后不再询问，为空数组时不安装可选依赖
+  */
+  features?: Array<string>
+  /**
+   * 要生成的模板名称，设置后不再选择，支持 button@^1.2 指定版本约束，button#main 指定 ref
+  */
+  template?: string
+  /**
+   * 本次生成使用的分支、tag 或 commit sha，代替 target_branch，安装的模板会固定在该 ref
+  */
+  gitRef?: string
+}
+/**
+ * 初始化本地yaml配置文件，文件已存在时需要确认，force 为 true 时直接覆盖
+*/
+export function initYamlFile(force?: boolean | undefined | null): void
+/**
+ * 根据用户输入生成yaml配置文件，输入时通过 API 校验 token、仓库、分支和远程 wego.yaml
+ * 文件已存在时需要确认，force 为 true 时直接覆盖
+*/
+export function initYamlFileWithStdin(force?: boolean | undefined | null): Promise<void>
+/**
+ * 通过 API 或命令行参数传入的配置，优先级最高
+*/
+export interface ConfigOverrides {
+  githubName?: string
+  repoName?: string
+  githubApiToken?: string
+  targetBranch?: string
+  templatesSource?: string
+  /**
+   * config | env:NAME | git-credential | netrc，只有显式指定时才会读取 git 凭据或 .netrc
+  */
+  tokenSource?: string
+  apiBase?: string
+  /**
+   * 使用配置文件 profiles 中的某一组配置，优先于 WEGO_PROFILE 和 default_profile
+  */
+  profile?: string
+}
+/**
+ * 配置文件中声明的 profile
+*/
+export interface ConfigProfileInfo {
+  name: string
+  githubName?: string
+  repoName?: string
+  targetBranch?: string
+  /**
+   * 声明该 profile 的配置文件
+  */
+  location: string
+  isDefault: boolean
+  isActive: boolean
+}
+/**
+ * 最终生效的配置项及其来源
+*/
+export interface ConfigValue {
+  key: string
+  /**
+   * token 只显示首尾几位
+  */
+  value: string
+  /**
+   * default | user | project | local | profile | token_source | env | argument
+  */
+  source: string
+  /**
+   * 配置文件路径或环境变量名
+  */
+  location?: string
+}
+/**
+ * 设置优先级最高的配置，会覆盖配置文件和环境变量中的同名配置
+*/
+export function setConfigOverrides(overrides: ConfigOverrides): void
+/**
+ * 显示合并后最终生效的配置以及每一项的来源
+*/
+export function showConfig(): Array<ConfigValue>
+/**
+ * doctor 的一项检查结果
+*/
+export interface DoctorCheck {
+  name: string
+  /**
+   * pass | warn | fail | skip
+  */
+  status: string
+  message: string
+  /**
+   * 检查未通过时建议的修复方式
+  */
+  fix?: string
+}
+/**
+ * 诊断配置、网络、token、限流额度、分支、远程 wego.yaml 和模板目录，每一项给出结果和修复建议
+*/
+export function doctor(): Promise<Array<DoctorCheck>>
+/**
+ * 检查 wego.yaml 的参数
+*/
+export interface LintOptions {
+  /**
+   * 模板仓库的本地目录，用于在模板仓库的 CI 中检查，不传时检查配置的远程仓库
+  */
+  path?: string
+  /**
+   * 本地检查时模板所在的目录，默认 templates
+  */
+  templatesSource?: string
+  /**
+   * 为 true 时有错误或无法检查时以退出码 1 退出进程，供模板仓库的 CI 使用
+   * 默认只返回检查出的问题，由调用方决定如何处理
+  */
+  exitOnError?: boolean
+}
+/**
+ * wego.yaml 检查出的问题
+*/
+export interface LintIssue {
+  /**
+   * invalid-manifest | unknown-dependency | dependency-cycle | duplicate-name | missing-folder | unlisted-folder | invalid-version | unknown-field
+  */
+  rule: string
+  /**
+   * error | warning
+  */
+  level: string
+  templateType: string
+  name: string
+  message: string
+}
+/**
+ * 检查 wego.yaml 中未声明的依赖、循环依赖、重复的模板名称、缺失和未声明的模板目录
+ * options.exit_on_error 为 true 时有错误以退出码 1 退出进程，可以在模板仓库的 CI 中使用
+*/
+export function lintManifest(options?: LintOptions | undefined | null): Promise<Array<LintIssue>>
+/**
+ * 远程 wego.yaml 的 JSON Schema，可以配置到编辑器中校验和补全
+*/
+export function getManifestSchema(): string
+/**
+ * 把远程 wego.yaml 的 JSON Schema 写入 output，默认写入当前目录的 wego.schema.json
+*/
+export function writeManifestSchema(output?: string | undefined | null): string | null
+/**
+ * 列出配置文件中声明的所有 profile，可以通过 setConfigOverrides 的 profile 切换
+*/
+export function listConfigProfiles(): Array<ConfigProfileInfo>
+/**
+ * 请求模板，dry run 时返回生成计划
+*/
+export function requestRemoteTemplates(templateType: TemplateType, options?: GenerateOptions | undefined | null): Promise<GenerationPlan | null>
+/**
+ * 按类型名称请求模板，支持远程 wego.yaml 的 types 中声明的自定义类型，dry run 时返回生成计划
+*/
+export function requestRemoteTemplatesByTypeName(typeName: string, options?: GenerateOptions | undefined | null): Promise<GenerationPlan | null>
+/**
+ * 远程仓库中的模板类型
+*/
+export interface TemplateTypeInfo {
+  name: string
+  label: string
+  /**
+   * 模板在远程仓库 templates_source 下的目录
+  */
+  source: string
+  /**
+   * 生成到本地的目录
+  */
+  destination: string
+  count: number
+}
+/**
+ * 列出远程仓库中的所有模板类型，包括自定义类型
+*/
+export function listTemplateTypes(): Promise<Array<TemplateTypeInfo>>
+/**
+ * 搜索模板的参数，筛选条件均不区分大小写
+*/
+export interface SearchOptions {
+  /**
+   * 模糊匹配名称、描述、标签和分类，为空时列出所有满足筛选条件的模板
+  */
+  query?: string
+  templateType?: string
+  tag?: string
+  category?: string
+  author?: string
+  /**
+   * 为 true 时从搜索结果中选择一个模板并生成
+  */
+  select?: boolean
+}
+/**
+ * 模板搜索结果，按匹配度从高到低排列
+*/
+export interface TemplateSearchResult {
+  name: string
+  templateType: string
+  description: string
+  tags: Array<string>
+  category?: string
+  author?: string
+  /**
+   * 匹配度，没有 query 时为 0
+  */
+  score: number
+}
+/**
+ * 在所有类型的模板中模糊搜索名称、描述、标签和分类，可以按类型、标签、分类和作者筛选
+ * options.select 为 true 时从结果中选择一个模板并生成
+*/
+export function searchTemplates(options?: SearchOptions | undefined | null): Promise<Array<TemplateSearchResult>>
+/**
+ * 依赖解析结果中的一个模板
+*/
+export interface ResolvedTemplate {
+  name: string
+  templateType: string
+  /**
+   * 安装的原因，例如 page `dashboard` → `chart` → `tooltip`
+  */
+  reason: string
+  /**
+   * 从选择的模板到该模板的依赖路径
+  */
+  path: Array<string>
+  /**
+   * 直接依赖的模板，components 只有名称，其他类型为 type/name
+  */
+  dependencies: Array<string>
+  /**
+   * 是否作为依赖安装，选择的模板本身为 false
+  */
+  dependency: boolean
+  /**
+   * 依赖声明中的版本约束，例如 ^1.2，未限定版本时为空
+  */
+  version?: string
+  /**
+   * 依赖声明中指定的分支、tag 或 commit sha，例如 button#main
+  */
+  gitRef?: string
+}
+/**
+ * 解析模板的所有直接和间接依赖，按安装顺序返回，每一项记录被安装的原因
+ * template_type 可以是 components、pages、projects 或远程 wego.yaml 中声明的类型
+ * features 为要一并解析的功能组或可选依赖
+*/
+export function resolveDependencies(templateType: string, name: string, features?: Array<string> | undefined | null): Promise<Array<ResolvedTemplate>>
+/**
+ * 模板仓库中的分支或 tag
+*/
+export interface RemoteRef {
+  name: string
+  /**
+   * branch | tag
+  */
+  refType: string
+  commit: string
+}
+/**
+ * 列出模板仓库的所有分支和 tag，可以作为 GenerateOptions 的 git_ref
+*/
+export function listRemoteRefs(): Promise<Array<RemoteRef>>
+/**
+ * 交互式选择模板仓库的分支或 tag，返回选择的 ref，取消时为空
+*/
+export function selectRemoteRef(): Promise<string | null>
+/**
+ * 已安装模板与远程模板的对比结果
+*/
+export interface OutdatedTemplate {
+  name: string
+  templateType: string
+  path: string
+  /**
+   * unchanged | updated | removed | modified | error
+  */
+  status: string
+  locallyModified: boolean
+  installedCommit: string
+  installedTree: string
+  latestTree?: string
+  /**
+   * 安装时有版本约束的模板记录的版本和约束范围内的最新版本
+  */
+  installedVersion?: string
+  latestVersion?: string
+  /**
+   * 获取远程版本失败的原因，此时 status 为 error
+  */
+  error?: string
+}
+/**
+ * 对比 wego.lock 中记录的模板与远程模板，检查是否有更新
+*/
+export function checkOutdatedTemplates(): Promise<Array<OutdatedTemplate>>
+/**
+ * 模板升级结果
+*/
+export interface UpgradeResult {
+  name: string
+  templateType: string
+  path: string
+  /**
+   * up-to-date | upgraded | conflict | removed | failed
+  */
+  status: string
+  fromCommit: string
+  toCommit: string
+  updated: Array<string>
+  deleted: Array<string>
+  conflicts: Array<string>
+  message?: string
+}
+/**
+ * 升级已安装的模板，本地修改会与远程更新做三方合并，names 为空时升级全部模板
+*/
+export function upgradeTemplates(names?: Array<string> | undefined | null): Promise<Array<UpgradeResult>>
+/**
+ * 本地模板文件与远程模板的差异
+*/
+export interface TemplateFileDiff {
+  path: string
+  /**
+   * modified | added | removed，added 表示仅本地存在，removed 表示仅远程存在
+  */
+  status: string
+  patch: string
+}
+/**
+ * 对比本地模板与远程模板的差异，latest 为 true 时与升级会选择的版本对比，否则与安装时的版本对比
+*/
+export function diffTemplate(name: string, latest?: boolean | undefined | null): Promise<Array<TemplateFileDiff>>
+/**
+ * 模板目录中的一次提交
+*/
+export interface ChangelogCommit {
+  sha: string
+  /**
+   * GitHub 用户名，提交邮箱未关联用户时为提交中的作者名称
+  */
+  author: string
+  date: string
+  message: string
+}
+/**
+ * 模板目录中变化的文件，path 相对于模板目录
+*/
+export interface ChangelogFile {
+  path: string
+  /**
+   * added | modified | removed | renamed 等，与 GitHub compare API 相同
+  */
+  status: string
+  additions: number
+  deletions: number
+}
+/**
+ * dry run 时输出的生成计划
+*/
+export interface GenerationPlan {
+  templates: Array<PlannedTemplate>
+}
+export interface PlannedTemplate {
+  name: string
+  templateType: string
+  destination: string
+  backup?: string
+  action: string
+  dependency: boolean
+  files: Array<PlannedFile>
+}
+export interface PlannedFile {
+  path: string
+  action: string
+}
+/**
+ * 已安装模板从安装时的提交到目标 ref 之间的变更
+*/
+export interface TemplateChangelog {
+  name: string
+  templateType: string
+  path: string
+  fromCommit: string
+  toRef: string
+  toCommit: string
+  /**
+   * 从新到旧排列
+  */
+  commits: Array<ChangelogCommit>
+  files: Array<ChangelogFile>
+  /**
+   * 两次提交之间变化的文件超过 compare API 的上限，files 不完整
+  */
+  truncated: boolean
+}
 /**
- * 根据用户输入生成yaml配置文件
+ * 列出已安装模板从安装时的提交到 git_ref 之间上游的提交和文件变化，用于升级前查看变更
+ * git_ref 为空时使用升级时会选择的版本
 */
-export function initYamlFileWithStdin(): void
+export function templateChangelog(name: string, gitRef?: string | undefined | null): Promise<TemplateChangelog | null>
 /**
- * 请求模板
+ * 删除已安装的模板，会检查其他模板是否依赖它，并可一并删除孤立的依赖
+ * template_type 可以是 components、pages、projects 或远程 wego.yaml 中声明的类型
+ * wego.lock 中没有记录的目录需要确认后才会删除，yes 为 true 时跳过确认
 */
-export function requestRemoteTemplates(templateType: TemplateType): Promise<void>
+export function removeTemplate(templateType: string, name: string, yes?: boolean | undefined | null): Promise<Array<string>>
@@ -0,0 +1,643 @@
+use std::{
+  collections::BTreeMap,
+  ffi::OsString,
+  fs::File,
+  io::BufReader,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+
+use colored::Colorize;
+
+use crate::{API_PREFIX, CONFIG_FILE, CONFIG_OVERRIDES};
+
+use super::{
+  credential::{get_git_host, read_git_credential, read_netrc_token},
+  ConfigFile, LocalConfigFile, ProfileConfig,
+};
+
+pub const CONFIG_FILE_NAME: &str = "wego.yaml";
+/**
+ * 与 wego.yaml 放在同一目录、不提交到仓库的配置，用于保存 token 等私密信息
+ */
+pub const LOCAL_CONFIG_FILE_NAME: &str = "wego.local.yaml";
+pub const ENV_PREFIX: &str = "WEGO_";
+
+pub const KEY_GITHUB_NAME: &str = "github_name";
+pub const KEY_REPO_NAME: &str = "repo_name";
+pub const KEY_GITHUB_API_TOKEN: &str = "github_api_token";
+pub const KEY_TARGET_BRANCH: &str = "target_branch";
+pub const KEY_TEMPLATES_SOURCE: &str = "templates_source";
+pub const KEY_TOKEN_SOURCE: &str = "token_source";
+pub const KEY_API_BASE: &str = "api_base";
+pub const KEY_PROFILE: &str = "profile";
+const DESTINATIONS_PREFIX: &str = "destinations.";
+
+const CONFIG_KEYS: [&str; 7] = [
+  KEY_GITHUB_NAME,
+  KEY_REPO_NAME,
+  KEY_GITHUB_API_TOKEN,
+  KEY_TARGET_BRANCH,
+  KEY_TEMPLATES_SOURCE,
+  KEY_TOKEN_SOURCE,
+  KEY_API_BASE,
+];
+
+pub const TOKEN_SOURCE_CONFIG: &str = "config";
+pub const TOKEN_SOURCE_GIT_CREDENTIAL: &str = "git-credential";
+pub const TOKEN_SOURCE_NETRC: &str = "netrc";
+pub const TOKEN_SOURCE_ENV_PREFIX: &str = "env:";
+
+/**
+ * 通过 API 或命令行参数传入的配置，优先级最高
+ */
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+  pub github_name: Option<String>,
+  pub repo_name: Option<String>,
+  pub github_api_token: Option<String>,
+  pub target_branch: Option<String>,
+  pub templates_source: Option<String>,
+  /**
+   * config | env:NAME | git-credential | netrc，只有显式指定时才会读取 git 凭据或 .netrc
+   */
+  pub token_source: Option<String>,
+  pub api_base: Option<String>,
+  /**
+   * 使用配置文件 profiles 中的某一组配置，优先于 WEGO_PROFILE 和 default_profile
+   */
+  pub profile: Option<String>,
+}
+
+/**
+ * 配置文件中声明的 profile
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ConfigProfileInfo {
+  pub name: String,
+  pub github_name: Option<String>,
+  pub repo_name: Option<String>,
+  pub target_branch: Option<String>,
+  /**
+   * 声明该 profile 的配置文件
+   */
+  pub location: String,
+  pub is_default: bool,
+  pub is_active: bool,
+}
+
+/**
+ * 最终生效的配置项及其来源
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ConfigValue {
+  pub key: String,
+  /**
+   * token 只显示首尾几位
+   */
+  pub value: String,
+  /**
+   * default | user | project | local | profile | token_source | env | argument
+   */
+  pub source: String,
+  /**
+   * 配置文件路径或环境变量名
+   */
+  pub location: Option<String>,
+}
+
+/**
+ * 一层配置，后面的层覆盖前面的层：
+ * 默认值 < 用户配置 < 项目配置 < wego.local.yaml < profile < token_source < 环境变量 < 参数
+ */
+struct ConfigLayer {
+  source: &'static str,
+  location: Option<String>,
+  values: BTreeMap<String, String>,
+}
+
+/**
+ * 合并所有配置层后的结果
+ */
+pub struct ResolvedConfig {
+  pub values: BTreeMap<String, ConfigValue>,
+  /**
+   * 找到的项目 wego.yaml，所在目录即项目根目录
+   */
+  pub project_file: Option<PathBuf>,
+  pub profiles: Vec<ConfigProfileInfo>,
+}
+
+impl ResolvedConfig {
+  pub fn get(&self, key: &str) -> Option<String> {
+    self
+      .values
+      .get(key)
+      .map(|item| item.value.clone())
+      .filter(|value| !value.is_empty())
+  }
+
+  pub fn get_project_root(&self) -> PathBuf {
+    match self.project_file.as_ref().and_then(|file| file.parent()) {
+      Some(root) => root.to_path_buf(),
+      None => std::env::current_dir().unwrap(),
+    }
+  }
+
+  pub fn to_config_file(&self) -> ConfigFile {
+    let destinations = self
+      .values
+      .iter()
+      .filter_map(|(key, item)| {
+        key
+          .strip_prefix(DESTINATIONS_PREFIX)
+          .map(|t_type| (t_type.to_string(), item.value.clone()))
+      })
+      .collect();
+
+    ConfigFile {
+      github_name: self.get(KEY_GITHUB_NAME).unwrap_or_default(),
+      repo_name: self.get(KEY_REPO_NAME).unwrap_or_default(),
+      github_api_token: self.get(KEY_GITHUB_API_TOKEN).unwrap_or_default(),
+      target_branch: self
+        .get(KEY_TARGET_BRANCH)
+        .unwrap_or_else(|| String::from("main")),
+      templates_source: self
+        .get(KEY_TEMPLATES_SOURCE)
+        .unwrap_or_else(|| String::from("templates")),
+      destinations,
+      project_root: self.get_project_root().to_str().unwrap().to_string(),
+      api_base: self
+        .get(KEY_API_BASE)
+        .unwrap_or_else(|| String::clone(&API_PREFIX)),
+    }
+  }
+
+  /**
+   * 用于展示的配置列表，token 会被遮盖
+   */
+  pub fn to_display_values(&self) -> Vec<ConfigValue> {
+    self
+      .values
+      .values()
+      .map(|item| {
+        let mut item = item.clone();
+        if item.key == KEY_GITHUB_API_TOKEN {
+          item.value = mask_secret(&item.value);
+        }
+        item
+      })
+      .collect()
+  }
+}
+
+/**
+ * 用户级配置文件，所有平台都位于 $XDG_CONFIG_HOME/wego/wego.yaml，
+ * 没有设置 XDG_CONFIG_HOME 时为 ~/.config/wego/wego.yaml
+ */
+pub fn get_user_config_file_path() -> Option<PathBuf> {
+  get_xdg_config_dir(std::env::var_os("XDG_CONFIG_HOME"), dirs::home_dir())
+    .map(|dir| dir.join("wego").join(CONFIG_FILE_NAME))
+}
+
+/**
+ * XDG_CONFIG_HOME 必须是绝对路径，否则按规范忽略并使用 ~/.config
+ */
+fn get_xdg_config_dir(xdg_config_home: Option<OsString>, home: Option<PathBuf>) -> Option<PathBuf> {
+  xdg_config_home
+    .map(PathBuf::from)
+    .filter(|dir| dir.is_absolute())
+    .or_else(|| home.map(|home| home.join(".config")))
+}
+
+/**
+ * 从当前目录开始逐级向上查找最近的 wego.yaml
+ */
+pub fn find_project_config_file() -> Option<PathBuf> {
+  let cwd = std::env::current_dir().ok()?;
+
+  cwd
+    .ancestors()
+    .map(|dir| dir.join(CONFIG_FILE_NAME))
+    .find(|file| file.is_file())
+}
+
+/**
+ * 按优先级合并默认值、用户配置、项目配置、profile、环境变量和参数
+ */
+pub fn resolve_config() -> Result<ResolvedConfig, String> {
+  let mut layers = vec![ConfigLayer {
+    source: "default",
+    location: None,
+    values: BTreeMap::from([
+      (KEY_TARGET_BRANCH.to_string(), String::from("main")),
+      (KEY_TEMPLATES_SOURCE.to_string(), String::from("templates")),
+      (KEY_API_BASE.to_string(), String::clone(&API_PREFIX)),
+    ]),
+  }];
+  let mut profiles: BTreeMap<String, (ProfileConfig, String)> = BTreeMap::new();
+  let mut default_profile: Option<(String, &'static str, String)> = None;
+
+  let project_file = find_project_config_file();
+  let files = [
+    ("user", get_user_config_file_path()),
+    ("project", project_file.clone()),
+    (
+      "local",
+      Some(get_local_config_file_path(project_file.as_deref())),
+    ),
+  ];
+
+  for (source, path) in files {
+    let path = match path.filter(|path| path.is_file()) {
+      Some(path) => path,
+      None => continue,
+    };
+    let mut config_file = read_config_file(&path)?;
+    let location = path.display().to_string();
+
+    for (name, profile) in config_file.profiles.take().unwrap_or_default() {
+      profiles.insert(name, (profile, location.clone()));
+    }
+    if let Some(name) = config_file.default_profile.take() {
+      default_profile = Some((name, source, location.clone()));
+    }
+
+    layers.push(file_layer(source, location, config_file));
+  }
+
+  let env_layers = read_env_layers();
+  let (overrides_layer, override_profile) = read_overrides_layer();
+
+  // 选择 profile 的优先级：参数 > WEGO_PROFILE > default_profile
+  let env_profile_name = format!("{}{}", ENV_PREFIX, KEY_PROFILE.to_uppercase());
+  let selected = match override_profile {
+    Some(name) => Some((name, "argument", None)),
+    None => match std::env::var(&env_profile_name)
+      .ok()
+      .filter(|name| !name.is_empty())
+    {
+      Some(name) => Some((name, "env", Some(env_profile_name))),
+      None => default_profile
+        .clone()
+        .map(|(name, source, location)| (name, source, Some(location))),
+    },
+  };
+
+  let mut profile_layer = None;
+  if let Some((name, source, location)) = &selected {
+    let (profile, profile_location) = profiles
+      .get(name)
+      .ok_or(format!("profile {} is not defined", name))?;
+
+    layers.push(profile_layer_from(name, profile_location, profile.clone()));
+    profile_layer = Some(ConfigLayer {
+      source,
+      location: location.clone(),
+      values: BTreeMap::from([(KEY_PROFILE.to_string(), name.clone())]),
+    });
+  }
+
+  let peek = |key: &str| {
+    layers
+      .iter()
+      .chain(env_layers.iter())
+      .chain(std::iter::once(&overrides_layer))
+      .rev()
+      .find_map(|layer| layer.values.get(key).cloned())
+  };
+  let api_base = peek(KEY_API_BASE).unwrap_or_else(|| String::clone(&API_PREFIX));
+  let token_source = peek(KEY_TOKEN_SOURCE);
+
+  let mut env_layers = env_layers;
+  if let Some(token_source) = &token_source {
+    layers.extend(read_token_source_layer(token_source, &api_base)?);
+    // 指定了 token_source 时不再使用通用的 GITHUB_TOKEN、WEGO_TOKEN
+    env_layers.retain(|layer| !is_generic_token_env(layer.location.as_deref()));
+  }
+
+  layers.extend(env_layers);
+  layers.push(overrides_layer);
+  layers.extend(profile_layer);
+
+  let mut values: BTreeMap<String, ConfigValue> = BTreeMap::new();
+  for layer in layers {
+    for (key, value) in layer.values {
+      values.insert(
+        key.clone(),
+        ConfigValue {
+          key,
+          value,
+          source: layer.source.to_string(),
+          location: layer.location.clone(),
+        },
+      );
+    }
+  }
+
+  let active = selected.map(|(name, _, _)| name);
+  let default_name = default_profile.map(|(name, _, _)| name);
+  let profiles = profiles
+    .into_iter()
+    .map(|(name, (profile, location))| ConfigProfileInfo {
+      is_default: default_name.as_ref() == Some(&name),
+      is_active: active.as_ref() == Some(&name),
+      name,
+      github_name: profile.github_name,
+      repo_name: profile.repo_name,
+      target_branch: profile.target_branch,
+      location,
+    })
+    .collect();
+
+  Ok(ResolvedConfig {
+    values,
+    project_file,
+    profiles,
+  })
+}
+
+/**
+ * 把合并后的配置写入全局的 CONFIG_FILE
+ */
+pub fn apply_config(resolved: &ResolvedConfig) {
+  let arc_file = Arc::clone(&CONFIG_FILE);
+  let mut config = arc_file.write().unwrap();
+
+  *config = resolved.to_config_file();
+}
+
+/**
+ * 替换全局的 CONFIG_FILE 并返回原来的配置，用于在新建的项目中安装模板后再恢复
+ */
+pub fn replace_config_file(config_file: ConfigFile) -> ConfigFile {
+  let arc_file = Arc::clone(&CONFIG_FILE);
+  let mut config = arc_file.write().unwrap();
+
+  std::mem::replace(&mut *config, config_file)
+}
+
+pub fn set_config_overrides(overrides: ConfigOverrides) {
+  let arc_overrides = Arc::clone(&CONFIG_OVERRIDES);
+  let mut config_overrides = arc_overrides.write().unwrap();
+
+  *config_overrides = overrides;
+}
+
+/**
+ * 生成目录、wego.lock 等相对路径的根目录，即 wego.yaml 所在目录
+ */
+pub fn get_project_root() -> String {
+  let arc_file = Arc::clone(&CONFIG_FILE);
+  let config = arc_file.read().unwrap();
+
+  if config.project_root.is_empty() {
+    std::env::current_dir()
+      .unwrap()
+      .to_str()
+      .unwrap()
+      .to_string()
+  } else {
+    config.project_root.clone()
+  }
+}
+
+pub fn print_config_values(resolved: &ResolvedConfig) {
+  for item in resolved.to_display_values() {
+    let source = match &item.location {
+      Some(location) => format!("{} {}", item.source, location),
+      None => item.source.clone(),
+    };
+
+    println!(
+      "{} = {} {}",
+      item.key.bold(),
+      item.value.green(),
+      format!("({})", source).white()
+    );
+  }
+}
+
+fn read_config_file(path: &Path) -> Result<LocalConfigFile, String> {
+  let file = File::open(path).map_err(|e| e.to_string())?;
+
+  serde_yaml::from_reader(BufReader::new(file))
+    .map_err(|e| format!("invalid {}: {}", path.display(), e))
+}
+
+fn collect_values<const N: usize>(fields: [(&str, Option<String>); N]) -> BTreeMap<String, String> {
+  fields
+    .into_iter()
+    .filter_map(|(key, value)| {
+      value
+        .filter(|value| !value.is_empty())
+        .map(|value| (key.to_string(), value))
+    })
+    .collect()
+}
+
+fn file_layer(source: &'static str, location: String, config_file: LocalConfigFile) -> ConfigLayer {
+  let mut values = collect_values([
+    (KEY_GITHUB_NAME, config_file.github_name),
+    (KEY_REPO_NAME, config_file.repo_name),
+    (KEY_GITHUB_API_TOKEN, config_file.github_api_token),
+    (KEY_TARGET_BRANCH, config_file.target_branch),
+    (KEY_TEMPLATES_SOURCE, config_file.templates_source),
+    (KEY_TOKEN_SOURCE, config_file.token_source),
+    (KEY_API_BASE, config_file.api_base),
+  ]);
+  for (t_type, destination) in config_file.destinations.unwrap_or_default() {
+    values.insert(format!("{}{}", DESTINATIONS_PREFIX, t_type), destination);
+  }
+
+  ConfigLayer {
+    source,
+    location: Some(location),
+    values,
+  }
+}
+
+fn profile_layer_from(name: &str, location: &str, profile: ProfileConfig) -> ConfigLayer {
+  ConfigLayer {
+    source: "profile",
+    location: Some(format!("{} {}", name, location)),
+    values: collect_values([
+      (KEY_GITHUB_NAME, profile.github_name),
+      (KEY_REPO_NAME, profile.repo_name),
+      (KEY_GITHUB_API_TOKEN, profile.github_api_token),
+      (KEY_TARGET_BRANCH, profile.target_branch),
+      (KEY_TEMPLATES_SOURCE, profile.templates_source),
+      (KEY_TOKEN_SOURCE, profile.token_source),
+      (KEY_API_BASE, profile.api_base),
+    ]),
+  }
+}
+
+/**
+ * wego.local.yaml 与项目 wego.yaml 在同一目录，没有项目配置时在当前目录
+ */
+pub fn get_local_config_file_path(project_file: Option<&Path>) -> PathBuf {
+  match project_file.and_then(|file| file.parent()) {
+    Some(root) => root.join(LOCAL_CONFIG_FILE_NAME),
+    None => std::env::current_dir()
+      .unwrap()
+      .join(LOCAL_CONFIG_FILE_NAME),
+  }
+}
+
+fn get_generic_token_env_names() -> [String; 2] {
+  [String::from("GITHUB_TOKEN"), format!("{}TOKEN", ENV_PREFIX)]
+}
+
+fn is_generic_token_env(name: Option<&str>) -> bool {
+  get_generic_token_env_names()
+    .iter()
+    .any(|item| Some(item.as_str()) == name)
+}
+
+/**
+ * WEGO_GITHUB_NAME、WEGO_REPO_NAME 等环境变量，每个变量单独作为一层以便记录来源
+ * token 还可以通过 GITHUB_TOKEN、WEGO_TOKEN 设置，优先级依次升高
+ */
+fn read_env_layers() -> Vec<ConfigLayer> {
+  let token_names = get_generic_token_env_names().map(|name| (KEY_GITHUB_API_TOKEN, name));
+  let names = CONFIG_KEYS
+    .iter()
+    .map(|key| (*key, format!("{}{}", ENV_PREFIX, key.to_uppercase())));
+
+  token_names
+    .into_iter()
+    .chain(names)
+    .filter_map(|(key, name)| {
+      let value = std::env::var(&name)
+        .ok()
+        .filter(|value| !value.is_empty())?;
+
+      Some(ConfigLayer {
+        source: "env",
+        location: Some(name),
+        values: BTreeMap::from([(key.to_string(), value)]),
+      })
+    })
+    .collect()
+}
+
+/**
+ * 按 token_source 读取 token，config 表示只使用配置文件中的 github_api_token
+ */
+fn read_token_source_layer(
+  token_source: &str,
+  api_base: &str,
+) -> Result<Option<ConfigLayer>, String> {
+  let token_layer = |source: &'static str, location: String, token: String| ConfigLayer {
+    source,
+    location: Some(location),
+    values: BTreeMap::from([(KEY_GITHUB_API_TOKEN.to_string(), token)]),
+  };
+
+  let layer = match token_source {
+    TOKEN_SOURCE_CONFIG => None,
+    TOKEN_SOURCE_GIT_CREDENTIAL => read_git_credential(api_base).map(|token| {
+      token_layer(
+        "token_source",
+        format!("{} {}", token_source, get_git_host(api_base)),
+        token,
+      )
+    }),
+    TOKEN_SOURCE_NETRC => read_netrc_token(api_base).map(|(token, path)| {
+      token_layer(
+        "token_source",
+        format!("{} {}", token_source, path.display()),
+        token,
+      )
+    }),
+    _ => match token_source.strip_prefix(TOKEN_SOURCE_ENV_PREFIX) {
+      Some(name) => std::env::var(name)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .map(|token| token_layer("token_source", token_source.to_string(), token)),
+      None => {
+        return Err(format!(
+          "invalid token_source {}, expected config, env:NAME, git-credential or netrc",
+          token_source
+        ))
+      }
+    },
+  };
+
+  if layer.is_none() && token_source != TOKEN_SOURCE_CONFIG {
+    println!(
+      "{} {}",
+      "Warning:".red().bold(),
+      format!("can not read github api token from {}", token_source).red()
+    );
+  }
+
+  Ok(layer)
+}
+
+fn read_overrides_layer() -> (ConfigLayer, Option<String>) {
+  let arc_overrides = Arc::clone(&CONFIG_OVERRIDES);
+  let overrides = arc_overrides.read().unwrap().clone();
+
+  let layer = ConfigLayer {
+    source: "argument",
+    location: None,
+    values: collect_values([
+      (KEY_GITHUB_NAME, overrides.github_name),
+      (KEY_REPO_NAME, overrides.repo_name),
+      (KEY_GITHUB_API_TOKEN, overrides.github_api_token),
+      (KEY_TARGET_BRANCH, overrides.target_branch),
+      (KEY_TEMPLATES_SOURCE, overrides.templates_source),
+      (KEY_TOKEN_SOURCE, overrides.token_source),
+      (KEY_API_BASE, overrides.api_base),
+    ]),
+  };
+
+  (layer, overrides.profile.filter(|name| !name.is_empty()))
+}
+
+fn mask_secret(value: &str) -> String {
+  let chars: Vec<char> = value.chars().collect();
+
+  if chars.len() <= 8 {
+    "*".repeat(chars.len())
+  } else {
+    format!(
+      "{}****{}",
+      chars[..4].iter().collect::<String>(),
+      chars[chars.len() - 4..].iter().collect::<String>()
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn uses_xdg_config_home_or_falls_back_to_dot_config() {
+    let home = Some(PathBuf::from("/home/wego"));
+
+    assert_eq!(
+      get_xdg_config_dir(Some(OsString::from("/xdg")), home.clone()),
+      Some(PathBuf::from("/xdg"))
+    );
+    assert_eq!(
+      get_xdg_config_dir(None, home.clone()),
+      Some(PathBuf::from("/home/wego/.config"))
+    );
+    assert_eq!(
+      get_xdg_config_dir(Some(OsString::from("relative")), home.clone()),
+      Some(PathBuf::from("/home/wego/.config"))
+    );
+    assert_eq!(
+      get_xdg_config_dir(Some(OsString::new()), home),
+      Some(PathBuf::from("/home/wego/.config"))
+    );
+    assert_eq!(get_xdg_config_dir(None, None), None);
+  }
+}
@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+
+use crate::ConflictStrategy;
+
+use super::{lock::read_local_files, merge::merge_dir, staging::StagingDir};
+
+/**
+ * 按冲突策略把模板文件写入 dest 目录
+ * 所有修改先在暂存目录中完成，成功后再整体替换 dest，出错时项目目录保持原样
+ * 覆盖、逐个询问和合并时只暂存模板中的文件，再逐个替换，dest 中的其他文件不会被复制或改动
+ * 返回实际写入的目录，跳过时返回 None
+ */
+pub fn write_template_files(
+  dest: &str,
+  files: &BTreeMap<String, Vec<u8>>,
+  strategy: ConflictStrategy,
+  base: Option<&BTreeMap<String, Vec<u8>>>,
+) -> Result<Option<String>, String> {
+  let exists = Path::new(dest).exists();
+
+  if exists && matches!(strategy, ConflictStrategy::Skip) {
+    println!(
+      "{} {} is already existed!",
+      "Warning:".red().bold(),
+      dest.red()
+    );
+    return Ok(None);
+  }
+
+  let staging = StagingDir::new(dest)?;
+  let stage = staging.path_string();
+  let mut target = dest.to_string();
+  let mut backup = None;
+  let mut written: Vec<String> = files.keys().cloned().collect();
+  let mut deleted = vec![];
+  let mut conflicts = vec![];
+  let mut in_place = false;
+
+  if !exists {
+    write_all_files(&stage, files)?;
+  } else {
+    match strategy {
+      ConflictStrategy::Skip => {}
+      ConflictStrategy::Overwrite => {
+        in_place = true;
+        write_all_files(&stage, files)?;
+      }
+      ConflictStrategy::Backup => {
+        backup = Some(get_available_path(&format!("{}.bak", dest)));
+        write_all_files(&stage, files)?;
+      }
+      ConflictStrategy::Rename => {
+        target = get_available_path(dest);
+        write_all_files(&stage, files)?;
+      }
+      ConflictStrategy::Prompt => {
+        in_place = true;
+        written.clear();
+
+        for (path, content) in files {
+          if let Ok(local_content) = fs::read(Path::new(dest).join(path)) {
+            if &local_content == content {
+              continue;
+            }
+
+            let overwrite = Confirm::with_theme(&ColorfulTheme::default())
+              .with_prompt(format!("{} is already existed, overwrite it?", path))
+              .default(false)
+              .interact()
+              .map_err(|e| e.to_string())?;
+
+            if !overwrite {
+              continue;
+            }
+          }
+
+          write_file(&Path::new(&stage).join(path), content)?;
+          written.push(path.clone());
+        }
+      }
+      ConflictStrategy::Merge => {
+        in_place = true;
+
+        let empty = BTreeMap::new();
+        let base = base.unwrap_or(&empty);
+        let ours = read_local_files(dest, base.keys().chain(files.keys()));
+        let report = merge_dir(&stage, base, &ours, files)?;
+
+        written = report.updated;
+        deleted = report.deleted;
+        conflicts = report.conflicts;
+      }
+    }
+  }
+
+  if in_place {
+    staging.commit_files(dest, &deleted)?;
+  } else {
+    staging.commit(&target, backup.as_deref())?;
+  }
+
+  if let Some(backup) = &backup {
+    println!("{}, Backup done!", backup.white());
+  }
+  for path in &written {
+    println!(
+      "{}, Write done!",
+      Path::new(&target).join(path).display().to_string().green()
+    );
+  }
+  for path in &deleted {
+    println!(
+      "{}, Delete done!",
+      Path::new(&target).join(path).display().to_string().red()
+    );
+  }
+  for conflict in &conflicts {
+    println!("{} {}", "Conflict:".yellow().bold(), conflict.yellow());
+  }
+
+  Ok(Some(target))
+}
+
+fn write_all_files(dest: &str, files: &BTreeMap<String, Vec<u8>>) -> Result<(), String> {
+  fs::create_dir_all(dest).map_err(|e| e.to_string())?;
+
+  for (path, content) in files {
+    write_file(&Path::new(dest).join(path), content)?;
+  }
+
+  Ok(())
+}
+
+fn write_file(local_path: &Path, content: &[u8]) -> Result<(), String> {
+  if let Some(parent) = local_path.parent() {
+    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+  }
+
+  fs::write(local_path, content).map_err(|e| e.to_string())
+}
+
+/**
+ * path 已存在时依次尝试 path-1、path-2 ...
+ */
+pub fn get_available_path(path: &str) -> String {
+  if !Path::new(path).exists() {
+    return path.to_string();
+  }
+
+  let mut index = 1;
+  loop {
+    let candidate = format!("{}-{}", path, index);
+    if !Path::new(&candidate).exists() {
+      return candidate;
+    }
+    index += 1;
+  }
+}
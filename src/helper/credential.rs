@@ -0,0 +1,199 @@
+use std::{
+  collections::BTreeMap,
+  fs,
+  io::Write,
+  path::PathBuf,
+  process::{Command, Stdio},
+  sync::Mutex,
+};
+
+use lazy_static::lazy_static;
+
+/**
+ * api 地址中的主机名，例如 api.github.com
+ */
+pub fn get_api_host(api_base: &str) -> String {
+  api_base
+    .split("://")
+    .nth(1)
+    .unwrap_or_default()
+    .split('/')
+    .next()
+    .unwrap_or_default()
+    .to_string()
+}
+
+/**
+ * git 凭据保存在 github.com 上，api.github.com 对应的 git 主机需要去掉 api. 前缀
+ */
+pub fn get_git_host(api_base: &str) -> String {
+  let host = get_api_host(api_base);
+
+  match host.strip_prefix("api.") {
+    Some(host) => host.to_string(),
+    None => host,
+  }
+}
+
+lazy_static! {
+  static ref GIT_CREDENTIALS: Mutex<BTreeMap<String, Option<String>>> = Mutex::new(BTreeMap::new());
+}
+
+/**
+ * 通过 git credential fill 读取 git 保存的凭据，禁止 git 在终端中询问
+ * 每个主机在进程内只读取一次，避免每次解析配置都启动 git 或弹出凭据管理器
+ */
+pub fn read_git_credential(api_base: &str) -> Option<String> {
+  let host = get_git_host(api_base);
+  let mut cache = GIT_CREDENTIALS.lock().unwrap();
+
+  cache
+    .entry(host)
+    .or_insert_with_key(|host| fill_git_credential(host))
+    .clone()
+}
+
+fn fill_git_credential(host: &str) -> Option<String> {
+  let mut child = Command::new("git")
+    .args(["credential", "fill"])
+    .env("GIT_TERMINAL_PROMPT", "0")
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null())
+    .spawn()
+    .ok()?;
+
+  child
+    .stdin
+    .take()?
+    .write_all(format!("protocol=https\nhost={}\n\n", host).as_bytes())
+    .ok()?;
+
+  let output = child.wait_with_output().ok()?;
+  if !output.status.success() {
+    return None;
+  }
+
+  String::from_utf8_lossy(&output.stdout)
+    .lines()
+    .find_map(|line| line.strip_prefix("password="))
+    .filter(|password| !password.is_empty())
+    .map(|password| password.to_string())
+}
+
+/**
+ * .netrc 文件路径，可以通过 NETRC 环境变量指定
+ */
+pub fn get_netrc_file_path() -> Option<PathBuf> {
+  if let Ok(path) = std::env::var("NETRC") {
+    return Some(PathBuf::from(path));
+  }
+
+  let file_name = if cfg!(target_os = "windows") {
+    "_netrc"
+  } else {
+    ".netrc"
+  };
+
+  dirs::home_dir().map(|home| home.join(file_name))
+}
+
+/**
+ * 从 .netrc 中读取 API 主机的 password，没有时依次尝试 git 主机和 default
+ */
+pub fn read_netrc_token(api_base: &str) -> Option<(String, PathBuf)> {
+  let path = get_netrc_file_path()?;
+  let content = fs::read_to_string(&path).ok()?;
+  let machines = parse_netrc(&content);
+
+  [
+    Some(get_api_host(api_base)),
+    Some(get_git_host(api_base)),
+    None,
+  ]
+  .iter()
+  .find_map(|host| {
+    machines
+      .iter()
+      .find(|(machine, _)| machine == host)
+      .map(|(_, password)| password.clone())
+  })
+  .map(|password| (password, path))
+}
+
+/**
+ * 返回 (machine, password) 列表，default 条目的 machine 为 None
+ */
+fn parse_netrc(content: &str) -> Vec<(Option<String>, String)> {
+  let mut result = vec![];
+  let mut machine: Option<Option<String>> = None;
+  let mut tokens = content.split_whitespace();
+
+  while let Some(token) = tokens.next() {
+    match token {
+      "machine" => machine = tokens.next().map(|name| Some(name.to_string())),
+      "default" => machine = Some(None),
+      "password" => {
+        if let (Some(machine), Some(password)) = (&machine, tokens.next()) {
+          result.push((machine.clone(), password.to_string()));
+        }
+      }
+      "login" | "account" => {
+        tokens.next();
+      }
+      _ => {}
+    }
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_machines_and_default_entry() {
+    let content = "machine api.github.com\n  login alice\n  password ghp_api\n\nmachine github.com login bob password ghp_git\ndefault login anonymous password ghp_default\n";
+
+    assert_eq!(
+      parse_netrc(content),
+      vec![
+        (
+          Some(String::from("api.github.com")),
+          String::from("ghp_api")
+        ),
+        (Some(String::from("github.com")), String::from("ghp_git")),
+        (None, String::from("ghp_default")),
+      ]
+    );
+  }
+
+  #[test]
+  fn skips_login_and_account_values_named_like_keywords() {
+    let content = "machine github.com login password account default password ghp_git";
+
+    assert_eq!(
+      parse_netrc(content),
+      vec![(Some(String::from("github.com")), String::from("ghp_git"))]
+    );
+  }
+
+  #[test]
+  fn ignores_password_before_any_machine() {
+    assert!(parse_netrc("password ghp_orphan").is_empty());
+  }
+
+  #[test]
+  fn maps_api_host_to_git_host() {
+    assert_eq!(
+      get_api_host("https://api.github.com/repos"),
+      "api.github.com"
+    );
+    assert_eq!(get_git_host("https://api.github.com/repos"), "github.com");
+    assert_eq!(
+      get_git_host("https://github.example.com/api/v3/repos"),
+      "github.example.com"
+    );
+  }
+}
@@ -0,0 +1,315 @@
+use std::{
+  collections::BTreeMap,
+  fs::{self, File},
+  io::BufReader,
+  path::Path,
+};
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{constants::FILE_MARKER, TemplateType};
+
+use super::config::get_project_root;
+
+pub const LOCK_FILE_NAME: &str = "wego.lock";
+const LOCK_FILE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LockFile {
+  pub version: u32,
+  #[serde(default)]
+  pub templates: Vec<LockedTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedTemplate {
+  pub name: String,
+  #[serde(rename = "type")]
+  pub template_type: String,
+  /**
+   * 模板类型在远程仓库中的目录，与类型名称相同时为空
+   */
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub source: String,
+  pub repo: String,
+  #[serde(rename = "ref")]
+  pub git_ref: String,
+  pub commit: String,
+  /**
+   * 安装的模板版本，来自 button@1.4.0 形式的 tag 或 wego.yaml 中的 version
+   */
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub version: String,
+  /**
+   * 安装时的版本约束，例如 ^1.2，升级时只在约束范围内选择版本
+   */
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub version_req: String,
+  /**
+   * 安装时指定了分支、tag 或 commit，升级时使用 ref 而不是 target_branch
+   */
+  #[serde(default, skip_serializing_if = "is_false")]
+  pub pinned: bool,
+  pub tree: String,
+  pub path: String,
+  /**
+   * 作为其他模板的依赖被安装，删除时可以作为孤立依赖一并移除
+   */
+  #[serde(default, skip_serializing_if = "is_false")]
+  pub dependency: bool,
+  /**
+   * 生成时的选择：name 为生成的目录名，features 为选择的功能组和可选依赖，
+   * strategy、dest 为生成时的冲突策略和生成目录，dest 只记录在选择的模板上
+   */
+  #[serde(default)]
+  pub answers: BTreeMap<String, String>,
+  #[serde(default)]
+  pub files: BTreeMap<String, String>,
+}
+
+fn is_false(value: &bool) -> bool {
+  !*value
+}
+
+impl Default for LockFile {
+  fn default() -> Self {
+    LockFile {
+      version: LOCK_FILE_VERSION,
+      templates: vec![],
+    }
+  }
+}
+
+impl LockedTemplate {
+  pub fn get_template_type(&self) -> Option<TemplateType> {
+    TemplateType::from_dir_name(&self.template_type)
+  }
+
+  pub fn get_source(&self) -> &str {
+    if self.source.is_empty() {
+      &self.template_type
+    } else {
+      &self.source
+    }
+  }
+
+  pub fn get_local_path(&self) -> String {
+    let root = get_project_root();
+
+    Path::new(&root)
+      .join(&self.path)
+      .to_str()
+      .unwrap()
+      .to_string()
+  }
+
+  /**
+   * 本地文件与安装时记录的 sha 不一致即视为被修改
+   */
+  pub fn is_locally_modified(&self) -> bool {
+    hash_local_dir(&self.get_local_path()) != self.files
+  }
+}
+
+impl LockFile {
+  pub fn read_from_local() -> Result<LockFile, String> {
+    let file_path = get_lock_file_path_string();
+
+    if !Path::new(&file_path).exists() {
+      return Ok(LockFile::default());
+    }
+
+    let file = File::open(&file_path).map_err(|e| e.to_string())?;
+    let reader = BufReader::new(file);
+
+    serde_yaml::from_reader::<_, LockFile>(reader)
+      .map_err(|e| format!("invalid {}: {}", LOCK_FILE_NAME, e))
+  }
+
+  pub fn write_to_local(&self) -> Result<(), String> {
+    let content = serde_yaml::to_string(self).map_err(|e| e.to_string())?;
+    let content = format!(
+      "# This file is generated by wego, do not edit it manually.\n{}",
+      content
+    );
+
+    fs::write(get_lock_file_path_string(), content).map_err(|e| e.to_string())
+  }
+
+  pub fn find(&self, template_type: &str, path: &str) -> Option<&LockedTemplate> {
+    self
+      .templates
+      .iter()
+      .find(|item| item.template_type == template_type && item.path == path)
+  }
+
+  /**
+   * 按安装路径或模板名称查找，路径优先
+   */
+  pub fn find_by_name_or_path(&self, key: &str) -> Option<&LockedTemplate> {
+    let key = key.trim_end_matches('/');
+
+    self
+      .templates
+      .iter()
+      .find(|item| item.path == key)
+      .or_else(|| self.templates.iter().find(|item| item.name == key))
+  }
+
+  pub fn remove(&mut self, template_type: &str, path: &str) {
+    self
+      .templates
+      .retain(|item| !(item.template_type == template_type && item.path == path));
+  }
+
+  pub fn upsert(&mut self, template: LockedTemplate) {
+    if let Some(index) = self
+      .templates
+      .iter()
+      .position(|item| item.template_type == template.template_type && item.path == template.path)
+    {
+      self.templates[index] = template;
+    } else {
+      self.templates.push(template);
+    }
+
+    self
+      .templates
+      .sort_by(|a, b| (&a.template_type, &a.path).cmp(&(&b.template_type, &b.path)));
+  }
+}
+
+/**
+ * 把本次安装的模板写入 wego.lock，已存在的同路径记录会被覆盖
+ */
+pub fn record_installed_templates(templates: Vec<LockedTemplate>) {
+  if templates.is_empty() {
+    return;
+  }
+
+  let result = LockFile::read_from_local().and_then(|mut lock_file| {
+    for template in templates {
+      lock_file.upsert(template);
+    }
+    lock_file.write_to_local()
+  });
+
+  if let Err(e) = result {
+    println!(
+      "{} {} {}",
+      "Warning:".red().bold(),
+      "Update wego.lock failure, cause:".red(),
+      e
+    );
+  }
+}
+
+pub fn get_lock_file_path_string() -> String {
+  let mut binding = get_project_root();
+
+  binding += &format!("{}{}", FILE_MARKER, LOCK_FILE_NAME);
+
+  binding
+}
+
+/**
+ * 与 git 的 blob sha 一致，可以直接和 github api 返回的文件 sha 对比
+ */
+pub fn git_blob_sha(content: &[u8]) -> String {
+  let mut hasher = sha1_smol::Sha1::new();
+  hasher.update(format!("blob {}\0", content.len()).as_bytes());
+  hasher.update(content);
+
+  hasher.digest().to_string()
+}
+
+/**
+ * 把本地路径转换为相对于 root 的路径，统一使用 `/` 分隔
+ */
+pub fn to_relative_path(root: &str, local_path: &str) -> String {
+  let relative = Path::new(local_path)
+    .strip_prefix(root)
+    .unwrap_or_else(|_| Path::new(local_path));
+
+  relative
+    .components()
+    .map(|item| item.as_os_str().to_string_lossy().to_string())
+    .collect::<Vec<String>>()
+    .join("/")
+}
+
+pub fn hash_files(root: &str, files: &[String]) -> BTreeMap<String, String> {
+  let mut result = BTreeMap::new();
+
+  for local_path in files {
+    if let Ok(content) = fs::read(local_path) {
+      result.insert(to_relative_path(root, local_path), git_blob_sha(&content));
+    }
+  }
+
+  result
+}
+
+pub fn hash_contents(files: &BTreeMap<String, Vec<u8>>) -> BTreeMap<String, String> {
+  files
+    .iter()
+    .map(|(path, content)| (path.clone(), git_blob_sha(content)))
+    .collect()
+}
+
+/**
+ * 计算本地目录下所有文件的 sha，用于判断已安装的模板是否被本地修改过
+ */
+pub fn hash_local_dir(root: &str) -> BTreeMap<String, String> {
+  let mut files = vec![];
+  collect_local_files(Path::new(root), &mut files);
+
+  hash_files(root, &files)
+}
+
+/**
+ * 读取本地目录下所有文件内容，key 为相对路径
+ */
+pub fn read_local_dir(root: &str) -> BTreeMap<String, Vec<u8>> {
+  let mut files = vec![];
+  collect_local_files(Path::new(root), &mut files);
+
+  files
+    .iter()
+    .filter_map(|local_path| {
+      fs::read(local_path)
+        .ok()
+        .map(|content| (to_relative_path(root, local_path), content))
+    })
+    .collect()
+}
+
+/**
+ * 只读取本地目录下 paths 中的文件，不存在的文件会被忽略
+ */
+pub fn read_local_files<'a>(
+  root: &str,
+  paths: impl Iterator<Item = &'a String>,
+) -> BTreeMap<String, Vec<u8>> {
+  paths
+    .filter_map(|path| {
+      fs::read(Path::new(root).join(path))
+        .ok()
+        .map(|content| (path.clone(), content))
+    })
+    .collect()
+}
+
+fn collect_local_files(dir: &Path, files: &mut Vec<String>) {
+  if let Ok(entries) = fs::read_dir(dir) {
+    for entry in entries.flatten() {
+      let path = entry.path();
+      if path.is_dir() {
+        collect_local_files(&path, files);
+      } else if let Some(path) = path.to_str() {
+        files.push(path.to_string());
+      }
+    }
+  }
+}
@@ -0,0 +1,188 @@
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs,
+  path::Path,
+};
+
+/**
+ * 单个文件三方合并的结果
+ * base: 安装时的模板版本，ours: 本地文件，theirs: 远程最新版本
+ */
+#[derive(Debug, PartialEq)]
+pub enum MergeOutcome {
+  /// 本地文件已经是期望的内容，无需写入
+  Unchanged,
+  /// 自动合并成功，需要写入的内容
+  Write(Vec<u8>),
+  /// 需要删除本地文件
+  Delete,
+  /// 存在冲突，`Some` 为带冲突标记的内容，`None` 表示保留本地文件
+  Conflict(Option<Vec<u8>>, String),
+}
+
+pub fn merge_file(base: Option<&[u8]>, ours: Option<&[u8]>, theirs: Option<&[u8]>) -> MergeOutcome {
+  if ours == theirs {
+    return MergeOutcome::Unchanged;
+  }
+
+  // 本地未修改，直接使用远程版本
+  if ours == base {
+    return match theirs {
+      Some(content) => MergeOutcome::Write(content.to_vec()),
+      None => MergeOutcome::Delete,
+    };
+  }
+
+  // 远程未修改，保留本地版本
+  if theirs == base {
+    return MergeOutcome::Unchanged;
+  }
+
+  match (ours, theirs) {
+    (Some(ours), Some(theirs)) => {
+      let base = base.unwrap_or_default();
+
+      match diffy::merge_bytes(base, ours, theirs) {
+        Ok(merged) => MergeOutcome::Write(merged),
+        Err(conflicted) => {
+          if is_binary(base) || is_binary(ours) || is_binary(theirs) {
+            MergeOutcome::Conflict(None, String::from("binary file changed on both sides"))
+          } else {
+            MergeOutcome::Conflict(Some(conflicted), String::from("changed on both sides"))
+          }
+        }
+      }
+    }
+    (Some(_), None) => {
+      MergeOutcome::Conflict(None, String::from("modified locally but removed upstream"))
+    }
+    (None, Some(_)) => {
+      MergeOutcome::Conflict(None, String::from("removed locally but modified upstream"))
+    }
+    (None, None) => MergeOutcome::Unchanged,
+  }
+}
+
+fn is_binary(content: &[u8]) -> bool {
+  content.contains(&0) || std::str::from_utf8(content).is_err()
+}
+
+#[derive(Debug, Default)]
+pub struct MergeReport {
+  pub updated: Vec<String>,
+  pub deleted: Vec<String>,
+  pub conflicts: Vec<String>,
+}
+
+/**
+ * 对整个模板目录做三方合并，需要写入的内容写入 root 目录
+ * 需要删除的文件只记录在 deleted 中，由调用方从本地模板目录中删除
+ */
+pub fn merge_dir(
+  root: &str,
+  base: &BTreeMap<String, Vec<u8>>,
+  ours: &BTreeMap<String, Vec<u8>>,
+  theirs: &BTreeMap<String, Vec<u8>>,
+) -> Result<MergeReport, String> {
+  let mut report = MergeReport::default();
+  let paths: BTreeSet<&String> = base
+    .keys()
+    .chain(ours.keys())
+    .chain(theirs.keys())
+    .collect();
+
+  for path in paths {
+    let local_path = Path::new(root).join(path);
+    let outcome = merge_file(
+      base.get(path).map(|item| item.as_slice()),
+      ours.get(path).map(|item| item.as_slice()),
+      theirs.get(path).map(|item| item.as_slice()),
+    );
+
+    match outcome {
+      MergeOutcome::Unchanged => {}
+      MergeOutcome::Write(content) => {
+        write_file(&local_path, &content)?;
+        report.updated.push(path.clone());
+      }
+      MergeOutcome::Delete => report.deleted.push(path.clone()),
+      MergeOutcome::Conflict(content, reason) => {
+        if let Some(content) = content {
+          write_file(&local_path, &content)?;
+        }
+        report.conflicts.push(format!("{}: {}", path, reason));
+      }
+    }
+  }
+
+  Ok(report)
+}
+
+fn write_file(local_path: &Path, content: &[u8]) -> Result<(), String> {
+  if let Some(parent) = local_path.parent() {
+    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+  }
+
+  fs::write(local_path, content).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn takes_upstream_when_local_is_unchanged() {
+    let outcome = merge_file(Some(b"a\n"), Some(b"a\n"), Some(b"b\n"));
+    assert_eq!(outcome, MergeOutcome::Write(b"b\n".to_vec()));
+
+    let outcome = merge_file(Some(b"a\n"), Some(b"a\n"), None);
+    assert_eq!(outcome, MergeOutcome::Delete);
+  }
+
+  #[test]
+  fn keeps_local_when_upstream_is_unchanged() {
+    let outcome = merge_file(Some(b"a\n"), Some(b"local\n"), Some(b"a\n"));
+    assert_eq!(outcome, MergeOutcome::Unchanged);
+  }
+
+  #[test]
+  fn merges_changes_on_different_lines() {
+    let base = b"one\ntwo\nthree\n";
+    let ours = b"ONE\ntwo\nthree\n";
+    let theirs = b"one\ntwo\nTHREE\n";
+
+    let outcome = merge_file(Some(base), Some(ours), Some(theirs));
+    assert_eq!(outcome, MergeOutcome::Write(b"ONE\ntwo\nTHREE\n".to_vec()));
+  }
+
+  #[test]
+  fn writes_conflict_markers_for_text_changed_on_both_sides() {
+    let outcome = merge_file(Some(b"a\n"), Some(b"ours\n"), Some(b"theirs\n"));
+
+    match outcome {
+      MergeOutcome::Conflict(Some(content), _) => {
+        let content = String::from_utf8(content).unwrap();
+        assert!(content.contains("<<<<<<<"));
+        assert!(content.contains("ours"));
+        assert!(content.contains("theirs"));
+        assert!(content.contains(">>>>>>>"));
+      }
+      other => panic!("unexpected outcome {:?}", other),
+    }
+  }
+
+  #[test]
+  fn keeps_local_binary_file_changed_on_both_sides() {
+    let outcome = merge_file(Some(&[0, 1]), Some(&[0, 2]), Some(&[0, 3]));
+    assert!(matches!(outcome, MergeOutcome::Conflict(None, _)));
+  }
+
+  #[test]
+  fn reports_conflict_when_one_side_removes_a_modified_file() {
+    let outcome = merge_file(Some(b"a\n"), Some(b"local\n"), None);
+    assert!(matches!(outcome, MergeOutcome::Conflict(None, _)));
+
+    let outcome = merge_file(Some(b"a\n"), None, Some(b"upstream\n"));
+    assert!(matches!(outcome, MergeOutcome::Conflict(None, _)));
+  }
+}
@@ -1,7 +1,18 @@
+use std::{
+  collections::BTreeMap,
+  path::{Component, Path, PathBuf},
+};
+
 use serde::{Deserialize, Serialize};
 
 use crate::{TemplateType, API_PREFIX};
 
+pub mod config;
+pub mod conflict;
+pub mod credential;
+pub mod lock;
+pub mod merge;
+pub mod staging;
 pub mod utils;
 #[derive(Clone, Debug)]
 pub struct ConfigFile {
@@ -10,15 +21,71 @@ pub struct ConfigFile {
   pub github_api_token: String,
   pub target_branch: String,
   pub templates_source: String,
+  /**
+   * 每类模板在本地的生成目录，key 为模板类型目录名（components、pages、projects）
+   */
+  pub destinations: BTreeMap<String, String>,
+  /**
+   * wego.yaml 所在目录，生成目录和 wego.lock 都相对于该目录
+   */
+  pub project_root: String,
+  /**
+   * github api 地址，默认为 https://api.github.com/repos
+   */
+  pub api_base: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LocalConfigFile {
-  pub github_name: String,
-  pub repo_name: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub github_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub repo_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub github_api_token: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub templates_source: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub target_branch: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub destinations: Option<BTreeMap<String, String>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub token_source: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub api_base: Option<String>,
+  /**
+   * 没有通过参数或 WEGO_PROFILE 指定时使用的 profile
+   */
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub default_profile: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub profiles: Option<BTreeMap<String, ProfileConfig>>,
+}
+
+/**
+ * 一组模板仓库配置，用于在多个模板仓库之间切换
+ */
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileConfig {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub github_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub repo_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub github_api_token: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub templates_source: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub target_branch: Option<String>,
+  /**
+   * token 的来源：config | env:NAME | git-credential | netrc，只有显式指定时才会读取 git 凭据或 .netrc
+   */
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub token_source: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub api_base: Option<String>,
 }
 
 impl ConfigFile {
@@ -35,11 +102,22 @@ impl ConfigFile {
       github_api_token,
       target_branch,
       templates_source,
+      destinations: BTreeMap::new(),
+      project_root: String::new(),
+      api_base: String::clone(&API_PREFIX),
+    }
+  }
+
+  pub fn get_api_base(&self) -> String {
+    if self.api_base.is_empty() {
+      String::clone(&API_PREFIX)
+    } else {
+      self.api_base.trim_end_matches('/').to_string()
     }
   }
 
   pub fn get_remote_yaml_url(&self) -> String {
-    let api = String::clone(&API_PREFIX);
+    let api = self.get_api_base();
     // let target_branch = self.target_branch.clone().unwrap();
 
     let strs: Vec<String> = vec![
@@ -53,25 +131,239 @@ impl ConfigFile {
     strs.join("/")
   }
 
-  pub fn get_remote_template_url(&self, file_type: TemplateType, file_name: &String) -> String {
-    let file_type_str: String;
+  pub fn get_repo_full_name(&self) -> String {
+    format!("{}/{}", self.github_name, self.repo_name)
+  }
+
+  pub fn get_remote_commit_url(&self, git_ref: &str) -> String {
+    format!(
+      "{}/{}/{}/commits/{}",
+      self.get_api_base(),
+      self.github_name,
+      self.repo_name,
+      utils::encode_query_value(git_ref),
+    )
+  }
+
+  /**
+   * api_base 以 /repos 结尾，去掉后得到 API 根地址，例如 https://api.github.com
+   */
+  pub fn get_api_root(&self) -> String {
+    let api = self.get_api_base();
+
+    match api.strip_suffix("/repos") {
+      Some(root) => root.to_string(),
+      None => api,
+    }
+  }
+
+  pub fn get_user_url(&self) -> String {
+    format!("{}/user", self.get_api_root())
+  }
+
+  pub fn get_repo_url(&self) -> String {
+    format!(
+      "{}/{}/{}",
+      self.get_api_base(),
+      self.github_name,
+      self.repo_name
+    )
+  }
+
+  pub fn get_remote_branches_url(&self) -> String {
+    format!("{}/branches", self.get_repo_url())
+  }
+
+  pub fn get_remote_commits_url(&self) -> String {
+    format!("{}/commits", self.get_repo_url())
+  }
+
+  pub fn get_remote_compare_url(&self, base: &str, head: &str) -> String {
+    format!("{}/compare/{}...{}", self.get_repo_url(), base, head)
+  }
+
+  pub fn get_remote_tags_url(&self) -> String {
+    format!("{}/tags", self.get_repo_url())
+  }
+
+  /**
+   * 以 prefix 开头的所有 tag，例如 button@ 匹配 button@1.4.0
+   */
+  pub fn get_remote_matching_tags_url(&self, prefix: &str) -> String {
+    format!("{}/git/matching-refs/tags/{}", self.get_repo_url(), prefix)
+  }
 
-    match file_type {
-      TemplateType::Pages => file_type_str = String::from("pages"),
-      TemplateType::Components => file_type_str = String::from("components"),
-      TemplateType::Project => file_type_str = String::from("projects"),
+  /**
+   * path 为空时返回仓库根目录
+   */
+  pub fn get_remote_contents_url(&self, path: &str) -> String {
+    if path.is_empty() {
+      format!("{}/contents", self.get_repo_url())
+    } else {
+      format!("{}/contents/{}", self.get_repo_url(), path)
     }
+  }
 
-    let url = format!(
-      "{}/{}/{}/contents/{}/{}/{}",
-      &API_PREFIX.to_string(),
+  /**
+   * source 为模板类型在 templates_source 下的目录
+   */
+  pub fn get_remote_templates_dir_url(&self, source: &str) -> String {
+    format!(
+      "{}/{}/{}/contents/{}/{}",
+      self.get_api_base(),
       self.github_name,
       self.repo_name,
       self.templates_source,
-      file_type_str,
-      file_name,
+      source,
+    )
+  }
+
+  pub fn get_remote_template_url(&self, source: &str, file_name: &str) -> String {
+    format!(
+      "{}/{}",
+      self.get_remote_templates_dir_url(source),
+      file_name
+    )
+  }
+
+  /**
+   * 模板类型在本地的生成目录，优先使用本地 wego.yaml 的 destinations，
+   * 其次是远程 wego.yaml 中声明的 destination，都没有时内置类型使用默认目录，自定义类型生成到 src/{type}
+   */
+  pub fn get_destination_root(&self, type_name: &str, manifest_dest: Option<&str>) -> String {
+    if let Some(root) = self.destinations.get(type_name) {
+      return root.clone();
+    }
+    if let Some(manifest_dest) = manifest_dest {
+      return manifest_dest.to_string();
+    }
+
+    match TemplateType::from_dir_name(type_name) {
+      Some(TemplateType::Components) => String::from("src/components"),
+      Some(TemplateType::Pages) => String::from("src/pages"),
+      Some(TemplateType::Project) => String::from(""),
+      None => format!("src/{}", type_name),
+    }
+  }
+
+  /**
+   * 模板生成到本地的绝对路径，dest 参数优先于 get_destination_root
+   * 目录中可以使用 {name}、{template}、{type} 占位符，不包含 {name} 时会在末尾拼接 name
+   * destination 可能来自远程 wego.yaml，绝对路径或包含 .. 的目录会被拒绝，生成目录只能在项目目录内
+   */
+  pub fn get_local_dir(
+    &self,
+    type_name: &str,
+    template_name: &str,
+    name: &str,
+    dest: Option<&str>,
+    manifest_dest: Option<&str>,
+  ) -> Result<String, String> {
+    let pattern = match dest {
+      Some(dest) => dest.to_string(),
+      None => self.get_destination_root(type_name, manifest_dest),
+    };
+
+    let destination = pattern
+      .replace("{type}", type_name)
+      .replace("{template}", template_name);
+    let destination = if destination.contains("{name}") {
+      destination.replace("{name}", name)
+    } else if destination.is_empty() {
+      name.to_string()
+    } else {
+      format!("{}/{}", destination, name)
+    };
+
+    let relative: PathBuf = Path::new(&destination)
+      .components()
+      .map(|item| match item {
+        Component::Normal(_) | Component::CurDir => Ok(item),
+        _ => Err(format!(
+          "{} is outside the project, the destination must be a relative path without ..",
+          destination
+        )),
+      })
+      .filter(|item| !matches!(item, Ok(Component::CurDir)))
+      .collect::<Result<_, String>>()?;
+    if relative.as_os_str().is_empty() {
+      return Err(format!(
+        "{} resolves to the project root, the destination must be a directory inside the project",
+        destination
+      ));
+    }
+
+    let root = if self.project_root.is_empty() {
+      std::env::current_dir().map_err(|e| e.to_string())?
+    } else {
+      PathBuf::from(&self.project_root)
+    };
+
+    root
+      .join(relative)
+      .to_str()
+      .map(String::from)
+      .ok_or_else(|| format!("{} is not a valid UTF-8 path", destination))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn config() -> ConfigFile {
+    let mut config = ConfigFile::new(
+      String::from("wego"),
+      String::from("templates"),
+      String::new(),
+      String::from("main"),
+      String::from("templates"),
+    );
+    config.project_root = String::from("/app");
+
+    config
+  }
+
+  #[test]
+  fn resolves_destinations_inside_the_project() {
+    let config = config();
+
+    assert_eq!(
+      config.get_local_dir("components", "button", "button", None, None),
+      Ok(String::from("/app/src/components/button"))
+    );
+    assert_eq!(
+      config.get_local_dir(
+        "hooks",
+        "useAuth",
+        "auth",
+        Some("./src/{type}/{name}"),
+        None
+      ),
+      Ok(String::from("/app/src/hooks/auth"))
+    );
+    assert_eq!(
+      config.get_local_dir("projects", "admin", "admin", None, None),
+      Ok(String::from("/app/admin"))
     );
+  }
+
+  #[test]
+  fn rejects_destinations_outside_the_project() {
+    let config = config();
 
-    url
+    for dest in ["/etc", "../../x", "src/{name}/../.."] {
+      assert!(config
+        .get_local_dir("components", "button", "button", None, Some(dest))
+        .is_err());
+      assert!(config
+        .get_local_dir("components", "button", "button", Some(dest), None)
+        .is_err());
+    }
+    for name in ["..", ".", ""] {
+      assert!(config
+        .get_local_dir("projects", name, name, None, None)
+        .is_err());
+    }
   }
 }
@@ -0,0 +1,321 @@
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  sync::atomic::{AtomicUsize, Ordering},
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+static STAGING_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/**
+ * commit_files 中已经处理的文件，saved 为原文件在暂存目录中的保存位置
+ */
+struct AppliedFile {
+  target: PathBuf,
+  saved: Option<PathBuf>,
+  placed: bool,
+}
+
+/**
+ * 模板先生成到目标目录旁边的隐藏暂存目录中，全部文件写入成功后再 rename 到目标位置
+ * 暂存目录与目标在同一个文件系统上，rename 是原子的；drop 时删除暂存目录，项目目录中不会留下半成品
+ */
+pub struct StagingDir {
+  root: PathBuf,
+  path: PathBuf,
+  /**
+   * 恢复原目录失败时保留暂存目录，避免删除其中的原目录
+   */
+  keep: bool,
+}
+
+impl StagingDir {
+  /**
+   * 在 dest 的父目录中创建 .wego-staging-* 暂存目录
+   */
+  pub fn new(dest: &str) -> Result<StagingDir, String> {
+    let parent = match Path::new(dest).parent() {
+      Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+      _ => PathBuf::from("."),
+    };
+    let nanos = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|item| item.as_nanos())
+      .unwrap_or_default();
+    let root = parent.join(format!(
+      ".wego-staging-{}-{}-{}",
+      std::process::id(),
+      nanos,
+      STAGING_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    let path = root.join("files");
+
+    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+
+    Ok(StagingDir {
+      root,
+      path,
+      keep: false,
+    })
+  }
+
+  pub fn path_string(&self) -> String {
+    self.path.to_str().unwrap().to_string()
+  }
+
+  /**
+   * 用暂存目录替换 dest，backup 不为空时原目录会保留到 backup，否则随暂存目录一起删除
+   * 任何一步失败都会恢复原目录
+   */
+  pub fn commit(mut self, dest: &str, backup: Option<&str>) -> Result<(), String> {
+    let dest_path = Path::new(dest);
+    let previous = if dest_path.exists() {
+      let previous = match backup {
+        Some(backup) => PathBuf::from(backup),
+        None => self.root.join("previous"),
+      };
+      fs::rename(dest_path, &previous).map_err(|e| e.to_string())?;
+      Some(previous)
+    } else {
+      None
+    };
+
+    if let Err(e) = fs::rename(&self.path, dest_path) {
+      if let Some(previous) = &previous {
+        if let Err(restore) = fs::rename(previous, dest_path) {
+          self.keep = true;
+          return Err(format!(
+            "{}, and the original directory can not be restored from {}: {}",
+            e,
+            previous.display(),
+            restore
+          ));
+        }
+      }
+      return Err(e.to_string());
+    }
+
+    Ok(())
+  }
+
+  /**
+   * 把暂存目录中的文件逐个移动到 dest 中，再删除 deleted 中的文件，dest 中的其他文件保持不变
+   * 被替换和删除的文件先移动到暂存目录中，任何一步失败都会恢复
+   */
+  pub fn commit_files(mut self, dest: &str, deleted: &[String]) -> Result<(), String> {
+    let mut files = vec![];
+    list_files(&self.path, Path::new(""), &mut files)?;
+
+    let mut applied = vec![];
+    if let Err(e) = self.apply_files(Path::new(dest), &files, deleted, &mut applied) {
+      for item in applied.iter().rev() {
+        if item.placed && fs::remove_file(&item.target).is_err() {
+          self.keep = true;
+          continue;
+        }
+        if let Some(saved) = &item.saved {
+          if move_file(saved, &item.target).is_err() {
+            self.keep = true;
+          }
+        }
+      }
+
+      if self.keep {
+        return Err(format!(
+          "{}, and some files can not be restored from {}",
+          e,
+          self.root.join("previous").display()
+        ));
+      }
+      return Err(e);
+    }
+
+    Ok(())
+  }
+
+  fn apply_files(
+    &self,
+    dest: &Path,
+    files: &[PathBuf],
+    deleted: &[String],
+    applied: &mut Vec<AppliedFile>,
+  ) -> Result<(), String> {
+    let previous = self.root.join("previous");
+    let changes = files
+      .iter()
+      .map(|file| (file.as_path(), true))
+      .chain(deleted.iter().map(|file| (Path::new(file), false)));
+
+    for (file, placed) in changes {
+      let target = dest.join(file);
+      let saved = if target.exists() {
+        let saved = previous.join(file);
+        move_file(&target, &saved)?;
+        Some(saved)
+      } else {
+        None
+      };
+
+      if placed {
+        if let Err(e) = move_file(&self.path.join(file), &target) {
+          applied.push(AppliedFile {
+            target,
+            saved,
+            placed: false,
+          });
+          return Err(e);
+        }
+      }
+      applied.push(AppliedFile {
+        target,
+        saved,
+        placed,
+      });
+    }
+
+    Ok(())
+  }
+}
+
+impl Drop for StagingDir {
+  fn drop(&mut self) {
+    if !self.keep && self.root.exists() {
+      let _ = fs::remove_dir_all(&self.root);
+    }
+  }
+}
+
+fn list_files(dir: &Path, prefix: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+  for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+    let entry = entry.map_err(|e| e.to_string())?;
+    let relative = prefix.join(entry.file_name());
+
+    if entry.path().is_dir() {
+      list_files(&entry.path(), &relative, files)?;
+    } else {
+      files.push(relative);
+    }
+  }
+
+  Ok(())
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+  if let Some(parent) = to.parent() {
+    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+  }
+
+  fs::rename(from, to).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_project(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wego-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+  }
+
+  fn list_dir(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+      .unwrap()
+      .map(|entry| entry.unwrap().file_name().to_str().unwrap().to_string())
+      .collect();
+    names.sort();
+
+    names
+  }
+
+  #[test]
+  fn replaces_the_destination_and_removes_the_staging_dir() {
+    let project = temp_project("staging-commit");
+    let dest = project.join("button");
+    fs::create_dir_all(&dest).unwrap();
+    fs::write(dest.join("old.ts"), "old").unwrap();
+    let dest = dest.to_str().unwrap();
+
+    let staging = StagingDir::new(dest).unwrap();
+    assert!(staging.path.starts_with(&project));
+    fs::write(Path::new(&staging.path_string()).join("index.ts"), "new").unwrap();
+    staging.commit(dest, None).unwrap();
+
+    assert_eq!(list_dir(&project), vec!["button"]);
+    assert_eq!(list_dir(Path::new(dest)), vec!["index.ts"]);
+    fs::remove_dir_all(&project).unwrap();
+  }
+
+  #[test]
+  fn removes_the_staging_dir_when_dropped_without_commit() {
+    let project = temp_project("staging-drop");
+    let dest = project.join("src/components/button");
+
+    let staging = StagingDir::new(dest.to_str().unwrap()).unwrap();
+    fs::write(Path::new(&staging.path_string()).join("index.ts"), "new").unwrap();
+    drop(staging);
+
+    assert!(list_dir(&project.join("src/components")).is_empty());
+    fs::remove_dir_all(&project).unwrap();
+  }
+
+  #[test]
+  fn replaces_only_staged_files_and_deletes_listed_files() {
+    let project = temp_project("staging-files");
+    let dest = project.join("app");
+    fs::create_dir_all(dest.join("src")).unwrap();
+    fs::write(dest.join("src/index.ts"), "old").unwrap();
+    fs::write(dest.join("src/removed.ts"), "removed").unwrap();
+    fs::write(dest.join("local.ts"), "local").unwrap();
+    let dest = dest.to_str().unwrap();
+
+    let staging = StagingDir::new(dest).unwrap();
+    let stage = PathBuf::from(staging.path_string());
+    fs::create_dir_all(stage.join("src")).unwrap();
+    fs::write(stage.join("src/index.ts"), "new").unwrap();
+    fs::write(stage.join("README.md"), "readme").unwrap();
+    staging
+      .commit_files(dest, &[String::from("src/removed.ts")])
+      .unwrap();
+
+    let dest = Path::new(dest);
+    assert_eq!(list_dir(&project), vec!["app"]);
+    assert_eq!(list_dir(dest), vec!["README.md", "local.ts", "src"]);
+    assert_eq!(list_dir(&dest.join("src")), vec!["index.ts"]);
+    assert_eq!(
+      fs::read_to_string(dest.join("src/index.ts")).unwrap(),
+      "new"
+    );
+    assert_eq!(fs::read_to_string(dest.join("local.ts")).unwrap(), "local");
+    fs::remove_dir_all(&project).unwrap();
+  }
+
+  #[test]
+  fn restores_replaced_files_when_a_file_can_not_be_moved() {
+    let project = temp_project("staging-restore");
+    let dest = project.join("app");
+    fs::create_dir_all(&dest).unwrap();
+    fs::write(dest.join("a.ts"), "old").unwrap();
+    // 目标中的 b 是文件，暂存的 b/x.ts 无法移动过去
+    fs::write(dest.join("b"), "file").unwrap();
+    let dest = dest.to_str().unwrap();
+
+    let staging = StagingDir::new(dest).unwrap();
+    let stage = PathBuf::from(staging.path_string());
+    fs::write(stage.join("a.ts"), "new").unwrap();
+    fs::write(stage.join("c.ts"), "new").unwrap();
+    fs::create_dir_all(stage.join("b")).unwrap();
+    fs::write(stage.join("b/x.ts"), "x").unwrap();
+
+    assert!(staging.commit_files(dest, &[]).is_err());
+
+    let dest = Path::new(dest);
+    assert_eq!(list_dir(&project), vec!["app"]);
+    assert_eq!(list_dir(dest), vec!["a.ts", "b"]);
+    assert_eq!(fs::read_to_string(dest.join("a.ts")).unwrap(), "old");
+    assert_eq!(fs::read_to_string(dest.join("b")).unwrap(), "file");
+    fs::remove_dir_all(&project).unwrap();
+  }
+}
@@ -1,150 +1,331 @@
 use colored::Colorize;
-use dialoguer::Input;
+use dialoguer::{theme::ColorfulTheme, Confirm};
 
-use crate::{constants::FILE_MARKER, CONFIG_FILE};
-use std::{
-  fs::File,
-  io::{BufReader, Write},
-  sync::Arc,
-};
+use crate::constants::FILE_MARKER;
+use std::{fs, path::Path};
 
+use super::config::{
+  apply_config, resolve_config, CONFIG_FILE_NAME, KEY_GITHUB_NAME, KEY_REPO_NAME,
+  LOCAL_CONFIG_FILE_NAME,
+};
 use super::LocalConfigFile;
 
-fn get_local_config_file_path_string() -> String {
-  let mut binding = std::env::current_dir()
-    .unwrap()
-    .to_str()
-    .unwrap()
-    .to_string();
+pub fn get_local_config_file_path_string() -> String {
+  get_local_dir_file_path_string(CONFIG_FILE_NAME)
+}
 
-  binding += &format!("{}{}", FILE_MARKER, "wego.yaml");
+/**
+ * wego.yaml 中每个配置项上方的注释，以及未配置时注释掉的示例值
+ * 示例值为 None 的配置项是必填项，未配置时也会保留一个空的 key
+ */
+const CONFIG_FILE_COMMENTS: [(&str, &str, Option<&str>); 6] = [
+  (
+    "github_name",
+    "The github user or organization of the templates repo",
+    None,
+  ),
+  ("repo_name", "The templates repo name", None),
+  (
+    "github_api_token",
+    "The github api token, keep it in wego.local.yaml or GITHUB_TOKEN/WEGO_TOKEN instead of this file",
+    Some(""),
+  ),
+  (
+    "templates_source",
+    "You can customize the templates dir path(default: templates)",
+    Some("templates"),
+  ),
+  (
+    "target_branch",
+    "You can customize the repo target branch name(default: main)",
+    Some("main"),
+  ),
+  (
+    "destinations",
+    "You can customize the local dir of each template type, {name} {template} {type} are supported",
+    Some("\n#   components: src/components\n#   pages: src/pages/{name}"),
+  ),
+];
 
-  binding
-}
+/**
+ * 由 LocalConfigFile 生成 wego.yaml 内容，只写入非默认值，并为常用配置项保留说明注释
+ */
+pub fn render_config_file(config_file: &LocalConfigFile) -> Result<String, String> {
+  let value = serde_yaml::to_value(config_file).map_err(|e| e.to_string())?;
+  let mapping = value.as_mapping().cloned().unwrap_or_default();
+  let mut sections = vec![];
 
-pub fn init_yaml_file() {
-  let file_path = get_local_config_file_path_string();
-  if let Ok(mut yaml) = File::create(file_path) {
-    let content = format!(
-        "github_name: \rrepo_name: \r# The github api token\r# github_api_token: \r \r# You can customize the templates dir path\r# templates_source: templates\r\r# You can customize the repo target branch name(default: main)\r# target_branch: main",
-    );
+  for (key, comment, example) in CONFIG_FILE_COMMENTS {
+    let section = match mapping.get(key) {
+      Some(value) => render_config_entry(key, value)?,
+      None => match example {
+        Some(example) if example.is_empty() || example.starts_with('\n') => {
+          format!("# {}:{}\n", key, example)
+        }
+        Some(example) => format!("# {}: {}\n", key, example),
+        None => format!("{}: \n", key),
+      },
+    };
+    sections.push(format!("# {}\n{}", comment, section));
+  }
 
-    yaml.write_all(content.as_bytes()).unwrap();
+  for (key, value) in &mapping {
+    let key = key.as_str().unwrap_or_default();
+    if CONFIG_FILE_COMMENTS.iter().all(|(name, _, _)| *name != key) {
+      sections.push(render_config_entry(key, value)?);
+    }
   }
-}
 
-pub fn init_yaml_file_with_stdin() -> Result<(), std::io::Error> {
-  let github_name: String = Input::new().with_prompt("Github Name").interact_text()?;
+  Ok(sections.join("\n"))
+}
 
-  let repo_name: String = Input::new().with_prompt("Repo Name").interact_text()?;
+fn render_config_entry(key: &str, value: &serde_yaml::Value) -> Result<String, String> {
+  let mut entry = serde_yaml::Mapping::new();
+  entry.insert(serde_yaml::Value::from(key), value.clone());
 
-  let github_api_token: String = Input::new()
-    .with_prompt("Github Api Token")
-    .allow_empty(true)
-    .interact_text()?;
+  serde_yaml::to_string(&entry).map_err(|e| e.to_string())
+}
 
-  let mut template_source: String = Input::new()
-    .with_prompt("Templates source remote dir path(Default: templates)")
-    .allow_empty(true)
-    .interact_text()?;
+/**
+ * 文件已存在时询问是否覆盖，force 为 true 时直接覆盖
+ */
+pub fn confirm_overwrite(file_path: &str, force: bool) -> bool {
+  if force || !Path::new(file_path).exists() {
+    return true;
+  }
 
-  let mut target_branch: String = Input::new()
-    .with_prompt("Template repo target branch")
-    .allow_empty(true)
-    .interact_text()?;
+  let overwrite = Confirm::with_theme(&ColorfulTheme::default())
+    .with_prompt(format!("{} is already existed, overwrite it?", file_path))
+    .default(false)
+    .interact()
+    .unwrap_or(false);
 
-  let mut token: String = String::from("");
-  if github_api_token != "" {
-    token = github_api_token;
+  if !overwrite {
+    println!("{}", "Init canceled, nothing is written.".yellow());
   }
 
-  if target_branch == "" {
-    target_branch = "main".to_string();
-  }
-  if template_source == "" {
-    template_source = String::from("templates");
-  }
+  overwrite
+}
+
+fn write_config_file(file_path: &str, content: &str) -> Result<(), std::io::Error> {
+  fs::write(file_path, content)?;
+  println!("{}, Write done!", file_path.green());
+
+  Ok(())
+}
+
+pub fn init_yaml_file(force: bool) -> Result<(), std::io::Error> {
   let file_path = get_local_config_file_path_string();
+  if !confirm_overwrite(&file_path, force) {
+    return Ok(());
+  }
 
-  if let Ok(mut yaml) = File::create(file_path) {
-    let content = format!(
-        "github_name: {}\rrepo_name: {}\r# The github api token\r github_api_token: {}\r \r# You can customize the templates dir path\r# templates_source: {}\r\r# You can customize the repo target branch name(default: main)\r# target_branch: {}",
-        github_name,
-        repo_name,
-        token,
-        template_source,
-        target_branch
-    );
+  let content = render_config_file(&LocalConfigFile::default()).map_err(std::io::Error::other)?;
+  write_config_file(&file_path, &content)?;
+
+  add_local_config_to_gitignore();
+
+  Ok(())
+}
+
+/**
+ * 写入 init 得到的配置，token 单独写入 wego.local.yaml，避免被提交到仓库
+ */
+pub fn write_init_config(
+  config_file: &LocalConfigFile,
+  github_api_token: &str,
+) -> Result<(), String> {
+  let content = render_config_file(config_file)?;
+  write_config_file(&get_local_config_file_path_string(), &content).map_err(|e| e.to_string())?;
 
-    yaml.write_all(content.as_bytes()).unwrap();
+  if !github_api_token.is_empty() {
+    write_local_token(github_api_token)?;
   }
 
+  add_local_config_to_gitignore();
+
   Ok(())
 }
 
-pub fn read_config_file_from_local() -> Result<(), ()> {
-  let is_ok_file = File::open(get_local_config_file_path_string().as_str());
-
-  if let Ok(file) = is_ok_file {
-    let reader = BufReader::new(file);
-    let config_file: LocalConfigFile = serde_yaml::from_reader(reader).expect("invalid yaml file");
-    let github_api_token = config_file.github_api_token;
-    let github_name = config_file.github_name;
-    let repo_name = config_file.repo_name;
-    let templates_source = config_file.templates_source;
-    let target_branch = config_file.target_branch;
-
-    if github_name == "" {
-      println!(
-        "{} {}",
-        "Warning:".red().bold(),
-        "github_name is required!".red()
-      );
-      std::process::exit(0);
-    }
+/**
+ * 把 token 写入 wego.local.yaml，已有的 profiles 等其他配置保持不变
+ */
+fn write_local_token(github_api_token: &str) -> Result<(), String> {
+  let local_file_path = get_local_dir_file_path_string(LOCAL_CONFIG_FILE_NAME);
+  let content = match fs::read_to_string(&local_file_path) {
+    Ok(content) => content,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+    Err(e) => return Err(format!("Read {} failure: {}", local_file_path, e)),
+  };
+  let content = set_local_token(&content, github_api_token)
+    .map_err(|e| format!("{} {}", local_file_path, e))?;
 
-    if repo_name == "" {
-      println!(
-        "{} {}",
-        "Warning:".red().bold(),
-        "repo_name is required!".red()
-      );
-      std::process::exit(0);
-    }
+  write_config_file(
+    &local_file_path,
+    &format!("# Private config, do not commit this file\n{}", content),
+  )
+  .map_err(|e| e.to_string())
+}
 
-    let mut token_string = String::from("");
+fn set_local_token(content: &str, github_api_token: &str) -> Result<String, String> {
+  let mut local_config = match serde_yaml::from_str::<serde_yaml::Value>(content) {
+    Ok(serde_yaml::Value::Mapping(mapping)) => mapping,
+    Ok(serde_yaml::Value::Null) => serde_yaml::Mapping::new(),
+    Ok(_) => return Err(String::from("is not a yaml mapping")),
+    Err(e) => return Err(format!("is not a valid yaml file: {}", e)),
+  };
+  local_config.insert(
+    serde_yaml::Value::from("github_api_token"),
+    serde_yaml::Value::from(github_api_token),
+  );
 
-    if let Some(token) = github_api_token {
-      token_string = token;
-    }
+  serde_yaml::to_string(&local_config).map_err(|e| e.to_string())
+}
 
-    let mut t_branch = String::from("main");
+pub fn get_local_dir_file_path_string(file_name: &str) -> String {
+  let mut binding = std::env::current_dir()
+    .unwrap()
+    .to_str()
+    .unwrap()
+    .to_string();
 
-    if let Some(branch) = target_branch {
-      t_branch = branch;
-    }
+  binding += &format!("{}{}", FILE_MARKER, file_name);
 
-    let mut t_source = String::from("templates");
+  binding
+}
 
-    if let Some(source) = templates_source {
-      t_source = source;
-    }
+/**
+ * 把 wego.local.yaml 加入当前目录的 .gitignore，已存在时不重复添加
+ */
+pub fn add_local_config_to_gitignore() {
+  let file_path = get_local_dir_file_path_string(".gitignore");
+  let content = fs::read_to_string(&file_path).unwrap_or_default();
 
-    let arc_file = Arc::clone(&CONFIG_FILE);
-    let mut config = arc_file.write().unwrap();
+  if content
+    .lines()
+    .any(|line| line.trim().trim_start_matches('/') == LOCAL_CONFIG_FILE_NAME)
+  {
+    return;
+  }
 
-    config.github_name = github_name;
-    config.repo_name = repo_name;
-    config.github_api_token = token_string;
-    config.target_branch = t_branch;
-    config.templates_source = t_source;
+  let mut content = content;
+  if !content.is_empty() && !content.ends_with('\n') {
+    content.push('\n');
+  }
+  content += &format!("{}\n", LOCAL_CONFIG_FILE_NAME);
 
-    Ok(())
-  } else {
+  if let Err(e) = fs::write(&file_path, content) {
+    println!(
+      "{} Add {} to {} failure, cause: {}",
+      "Warning:".red().bold(),
+      LOCAL_CONFIG_FILE_NAME,
+      Path::new(&file_path).display(),
+      e
+    );
+  }
+}
+
+/**
+ * 按 用户配置 < 项目 wego.yaml < wego.local.yaml < profile < 环境变量 < 参数 的顺序合并配置，写入 CONFIG_FILE
+ */
+#[allow(clippy::result_unit_err)]
+pub fn read_config_file_from_local() -> Result<(), ()> {
+  let resolved = match resolve_config() {
+    Ok(resolved) => resolved,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      return Err(());
+    }
+  };
+
+  let github_name = resolved.get(KEY_GITHUB_NAME);
+  let repo_name = resolved.get(KEY_REPO_NAME);
+
+  if resolved.project_file.is_none() && (github_name.is_none() || repo_name.is_none()) {
     println!(
       "{}",
       "Need a wego.yaml, you can use command wego init -y to generate the file.".red()
     );
-    Err(())
+    return Err(());
+  }
+
+  if github_name.is_none() {
+    println!(
+      "{} {}",
+      "Warning:".red().bold(),
+      "github_name is required!".red()
+    );
+    std::process::exit(0);
+  }
+
+  if repo_name.is_none() {
+    println!(
+      "{} {}",
+      "Warning:".red().bold(),
+      "repo_name is required!".red()
+    );
+    std::process::exit(0);
+  }
+
+  apply_config(&resolved);
+
+  Ok(())
+}
+
+/**
+ * 对 query 参数做百分号编码，保留 unreserved 字符和路径分隔符 /
+ */
+pub fn encode_query_value(value: &str) -> String {
+  value
+    .bytes()
+    .map(|byte| match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+        (byte as char).to_string()
+      }
+      _ => format!("%{:02X}", byte),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keeps_other_local_config_when_setting_the_token() {
+    let content = set_local_token(
+      "# Private config\ngithub_api_token: old\ndefault_profile: work\nprofiles:\n  work:\n    github_api_token: work-token\n",
+      "new",
+    )
+    .unwrap();
+    let local_config: LocalConfigFile = serde_yaml::from_str(&content).unwrap();
+
+    assert_eq!(local_config.github_api_token.as_deref(), Some("new"));
+    assert_eq!(local_config.default_profile.as_deref(), Some("work"));
+    assert!(local_config.profiles.unwrap().contains_key("work"));
+  }
+
+  #[test]
+  fn creates_the_local_config_when_it_is_empty() {
+    assert_eq!(
+      set_local_token("", "token").unwrap(),
+      "github_api_token: token\n"
+    );
+    assert!(set_local_token("- token", "token").is_err());
+  }
+
+  #[test]
+  fn encodes_query_values() {
+    assert_eq!(
+      encode_query_value("templates/components/my button"),
+      "templates/components/my%20button"
+    );
+    assert_eq!(
+      encode_query_value("2024-01-02T03:04:05+08:00"),
+      "2024-01-02T03%3A04%3A05%2B08%3A00"
+    );
+    assert_eq!(encode_query_value("a&b=c#d"), "a%26b%3Dc%23d");
+    assert_eq!(encode_query_value("按钮"), "%E6%8C%89%E9%92%AE");
+    assert_eq!(encode_query_value("feature/a&b#c"), "feature/a%26b%23c");
   }
 }
@@ -3,15 +3,31 @@
 pub mod constants;
 pub mod helper;
 pub mod request;
-use helper::ConfigFile;
+use helper::{
+  config::{ConfigOverrides, ConfigProfileInfo, ConfigValue},
+  ConfigFile,
+};
 use hyper::{Body, Client};
 use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
-use napi::bindgen_prelude::*;
 use std::sync::{Arc, RwLock};
 
+use colored::Colorize;
 use lazy_static::lazy_static;
 
-use crate::request::request::get_remote_yaml_config;
+use crate::request::{
+  changelog::TemplateChangelog,
+  diff::TemplateFileDiff,
+  doctor::DoctorCheck,
+  lint::{LintIssue, LintOptions},
+  outdated::OutdatedTemplate,
+  plan::GenerationPlan,
+  refs::RemoteRef,
+  request::get_remote_yaml_config,
+  resolve::ResolvedTemplate,
+  search::{SearchOptions, TemplateSearchResult},
+  types::TemplateTypeInfo,
+  upgrade::UpgradeResult,
+};
 
 #[macro_use]
 extern crate napi_derive;
@@ -26,6 +42,154 @@ pub enum TemplateType {
   Project,
 }
 
+impl TemplateType {
+  /**
+   * 模板类型在远程仓库中对应的目录名
+   */
+  pub fn dir_name(&self) -> &'static str {
+    match self {
+      TemplateType::Pages => "pages",
+      TemplateType::Components => "components",
+      TemplateType::Project => "projects",
+    }
+  }
+
+  pub fn from_dir_name(name: &str) -> Option<TemplateType> {
+    match name {
+      "pages" => Some(TemplateType::Pages),
+      "components" => Some(TemplateType::Components),
+      "projects" => Some(TemplateType::Project),
+      _ => None,
+    }
+  }
+}
+
+/**
+ * 目标目录已存在时的处理方式
+ */
+#[napi]
+pub enum ConflictStrategy {
+  /// 跳过已存在的模板
+  Skip,
+  /// 直接覆盖同名文件
+  Overwrite,
+  /// 先把已存在的目录重命名为 .bak 备份，再重新生成
+  Backup,
+  /// 生成到带数字后缀的新目录
+  Rename,
+  /// 逐个文件询问是否覆盖
+  Prompt,
+  /// 与本地文件合并，冲突处写入冲突标记
+  Merge,
+}
+
+impl ConflictStrategy {
+  /**
+   * 记录到 wego.lock 中的名称
+   */
+  pub fn name(&self) -> &'static str {
+    match self {
+      ConflictStrategy::Skip => "skip",
+      ConflictStrategy::Overwrite => "overwrite",
+      ConflictStrategy::Backup => "backup",
+      ConflictStrategy::Rename => "rename",
+      ConflictStrategy::Prompt => "prompt",
+      ConflictStrategy::Merge => "merge",
+    }
+  }
+}
+
+/**
+ * dry run 时生成计划的输出格式
+ */
+#[napi]
+pub enum PlanFormat {
+  Tree,
+  Json,
+}
+
+/**
+ * 生成模板时的可选参数
+ */
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct GenerateOptions {
+  /**
+   * 目标目录已存在时的处理方式，默认跳过
+   */
+  pub conflict_strategy: Option<ConflictStrategy>,
+  /**
+   * 只解析远程模板并输出生成计划，不写入任何文件
+   */
+  pub dry_run: Option<bool>,
+  /**
+   * dry run 计划的输出格式，默认 Tree
+   */
+  pub plan_format: Option<PlanFormat>,
+  /**
+   * 生成目录，优先于 wego.yaml 中的 destinations，支持 {name}、{template}、{type} 占位符
+   */
+  pub dest: Option<String>,
+  /**
+   * 要安装的功能组或可选依赖，设置后不再询问，为空数组时不安装可选依赖
+   */
+  pub features: Option<Vec<String>>,
+  /**
+   * 要生成的模板名称，设置后不再选择，支持 button@^1.2 指定版本约束，button#main 指定 ref
+   */
+  pub template: Option<String>,
+  /**
+   * 本次生成使用的分支、tag 或 commit sha，代替 target_branch，安装的模板会固定在该 ref
+   */
+  pub git_ref: Option<String>,
+}
+
+impl GenerateOptions {
+  pub fn get_conflict_strategy(&self) -> ConflictStrategy {
+    self.conflict_strategy.unwrap_or(ConflictStrategy::Skip)
+  }
+
+  pub fn is_dry_run(&self) -> bool {
+    self.dry_run.unwrap_or(false)
+  }
+
+  pub fn get_plan_format(&self) -> PlanFormat {
+    self.plan_format.unwrap_or(PlanFormat::Tree)
+  }
+
+  /**
+   * json 格式的 dry run，stdout 只能输出生成计划
+   */
+  pub fn is_json_plan(&self) -> bool {
+    self.is_dry_run() && matches!(self.get_plan_format(), PlanFormat::Json)
+  }
+
+  /**
+   * 记录到 wego.lock 的生成选项，与模板名称、选择的功能组一起可以复现一次生成
+   */
+  pub fn get_answers(&self) -> std::collections::BTreeMap<String, String> {
+    let mut answers = self.get_dependency_answers();
+    if let Some(dest) = &self.dest {
+      answers.insert(String::from("dest"), dest.clone());
+    }
+
+    answers
+  }
+
+  /**
+   * 依赖记录的生成选项，dest 只作用于选择的模板，依赖的目录不受它影响，因此不记录
+   */
+  pub fn get_dependency_answers(&self) -> std::collections::BTreeMap<String, String> {
+    let mut answers = std::collections::BTreeMap::new();
+    answers.insert(
+      String::from("strategy"),
+      self.get_conflict_strategy().name().to_string(),
+    );
+
+    answers
+  }
+}
+
 lazy_static! {
   pub static ref API_PREFIX: String = String::from("https://api.github.com/repos");
   pub static ref CONFIG_FILE: Arc<RwLock<ConfigFile>> = Arc::new(RwLock::new(ConfigFile {
@@ -33,44 +197,379 @@ lazy_static! {
     github_name: String::from(""),
     repo_name: String::from(""),
     target_branch: String::from("main"),
-    templates_source: String::from("templates")
+    templates_source: String::from("templates"),
+    destinations: std::collections::BTreeMap::new(),
+    project_root: String::from(""),
+    api_base: String::clone(&API_PREFIX)
   }));
+  pub static ref CONFIG_OVERRIDES: Arc<RwLock<ConfigOverrides>> =
+    Arc::new(RwLock::new(ConfigOverrides::default()));
   static ref CLIENT: Client<HttpsConnector<hyper::client::HttpConnector>> = {
     let https = HttpsConnectorBuilder::new()
       .with_native_roots()
       .https_only()
       .enable_http1()
       .build();
-    let client = Client::builder().build::<_, Body>(https);
+    Client::builder().build::<_, Body>(https)
+  };
+}
+
+/**
+ * 初始化本地yaml配置文件，文件已存在时需要确认，force 为 true 时直接覆盖
+ */
+#[napi]
+pub fn init_yaml_file(force: Option<bool>) {
+  if let Err(e) = helper::utils::init_yaml_file(force.unwrap_or(false)) {
+    println!("Create yaml file failure, cause: {:?}", e);
+  }
+}
+
+/**
+ * 根据用户输入生成yaml配置文件，输入时通过 API 校验 token、仓库、分支和远程 wego.yaml
+ * 文件已存在时需要确认，force 为 true 时直接覆盖
+ */
+#[napi]
+pub async fn init_yaml_file_with_stdin(force: Option<bool>) {
+  if let Err(e) = request::init::init_config_with_stdin(force.unwrap_or(false)).await {
+    println!("Create yaml file failure, cause: {}", e.red());
+  }
+}
+
+/**
+ * 设置优先级最高的配置，会覆盖配置文件和环境变量中的同名配置
+ */
+#[napi]
+pub fn set_config_overrides(overrides: ConfigOverrides) {
+  helper::config::set_config_overrides(overrides);
+}
+
+/**
+ * 显示合并后最终生效的配置以及每一项的来源
+ */
+#[napi]
+pub fn show_config() -> Vec<ConfigValue> {
+  match helper::config::resolve_config() {
+    Ok(resolved) => {
+      helper::config::print_config_values(&resolved);
+      resolved.to_display_values()
+    }
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      vec![]
+    }
+  }
+}
 
-    client
+/**
+ * 诊断配置、网络、token、限流额度、分支、远程 wego.yaml 和模板目录，每一项给出结果和修复建议
+ */
+#[napi]
+pub async fn doctor() -> Vec<DoctorCheck> {
+  request::doctor::run_doctor().await
+}
+
+/**
+ * 检查 wego.yaml 中未声明的依赖、循环依赖、重复的模板名称、缺失和未声明的模板目录
+ * options.exit_on_error 为 true 时有错误以退出码 1 退出进程，可以在模板仓库的 CI 中使用
+ */
+#[napi]
+pub async fn lint_manifest(options: Option<LintOptions>) -> Vec<LintIssue> {
+  let options = options.unwrap_or_default();
+  let exit_on_error = options.exit_on_error.unwrap_or(false);
+  if options.path.is_none() && helper::utils::read_config_file_from_local().is_err() {
+    if exit_on_error {
+      std::process::exit(1);
+    }
+    return vec![];
+  }
+
+  let issues = match request::lint::lint_manifest(&options).await {
+    Ok(issues) => issues,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      if exit_on_error {
+        std::process::exit(1);
+      }
+      vec![]
+    }
   };
+
+  if exit_on_error && request::lint::has_lint_errors(&issues) {
+    std::process::exit(1);
+  }
+
+  issues
 }
 
 /**
- * 初始化本地yaml配置文件
+ * 远程 wego.yaml 的 JSON Schema，可以配置到编辑器中校验和补全
  */
 #[napi]
-pub fn init_yaml_file() {
-  helper::utils::init_yaml_file();
+pub fn get_manifest_schema() -> String {
+  match request::schema::get_manifest_schema() {
+    Ok(schema) => schema,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      String::new()
+    }
+  }
 }
 
 /**
- * 根据用户输入生成yaml配置文件
+ * 把远程 wego.yaml 的 JSON Schema 写入 output，默认写入当前目录的 wego.schema.json
  */
 #[napi]
-pub fn init_yaml_file_with_stdin() {
-  if let Err(e) = helper::utils::init_yaml_file_with_stdin() {
-    println!("Create yaml file failure, cause: {:?}", e);
+pub fn write_manifest_schema(output: Option<String>) -> Option<String> {
+  match request::schema::write_manifest_schema(output) {
+    Ok(file_path) => Some(file_path),
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      None
+    }
+  }
+}
+
+/**
+ * 列出配置文件中声明的所有 profile，可以通过 setConfigOverrides 的 profile 切换
+ */
+#[napi]
+pub fn list_config_profiles() -> Vec<ConfigProfileInfo> {
+  match helper::config::resolve_config() {
+    Ok(resolved) => {
+      for item in &resolved.profiles {
+        let mark = if item.is_active { "*" } else { " " };
+        let default = if item.is_default { " (default)" } else { "" };
+        println!(
+          "{} {}{} ----> {}/{}",
+          mark.green().bold(),
+          item.name.bold(),
+          default.white(),
+          item.github_name.clone().unwrap_or_default(),
+          item.repo_name.clone().unwrap_or_default()
+        );
+      }
+      resolved.profiles
+    }
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      vec![]
+    }
   }
 }
 
 /**
- * 请求模板
+ * 请求模板，dry run 时返回生成计划
  */
 #[napi]
-pub async fn request_remote_templates(template_type: TemplateType) {
-  if let Ok(_) = helper::utils::read_config_file_from_local() {
-    get_remote_yaml_config(template_type).await;
+pub async fn request_remote_templates(
+  template_type: TemplateType,
+  options: Option<GenerateOptions>,
+) -> Option<GenerationPlan> {
+  if helper::utils::read_config_file_from_local().is_err() {
+    return None;
+  }
+
+  get_remote_yaml_config(template_type.dir_name(), options.unwrap_or_default()).await
+}
+
+/**
+ * 按类型名称请求模板，支持远程 wego.yaml 的 types 中声明的自定义类型，dry run 时返回生成计划
+ */
+#[napi]
+pub async fn request_remote_templates_by_type_name(
+  type_name: String,
+  options: Option<GenerateOptions>,
+) -> Option<GenerationPlan> {
+  if helper::utils::read_config_file_from_local().is_err() {
+    return None;
+  }
+
+  get_remote_yaml_config(&type_name, options.unwrap_or_default()).await
+}
+
+/**
+ * 列出远程仓库中的所有模板类型，包括自定义类型
+ */
+#[napi]
+pub async fn list_template_types() -> Vec<TemplateTypeInfo> {
+  if helper::utils::read_config_file_from_local().is_err() {
+    return vec![];
+  }
+
+  match request::types::list_template_types().await {
+    Ok(result) => result,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      vec![]
+    }
+  }
+}
+
+/**
+ * 在所有类型的模板中模糊搜索名称、描述、标签和分类，可以按类型、标签、分类和作者筛选
+ * options.select 为 true 时从结果中选择一个模板并生成
+ */
+#[napi]
+pub async fn search_templates(options: Option<SearchOptions>) -> Vec<TemplateSearchResult> {
+  if helper::utils::read_config_file_from_local().is_err() {
+    return vec![];
+  }
+
+  match request::search::search_templates(&options.unwrap_or_default()).await {
+    Ok(result) => result,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      vec![]
+    }
+  }
+}
+
+/**
+ * 解析模板的所有直接和间接依赖，按安装顺序返回，每一项记录被安装的原因
+ * template_type 可以是 components、pages、projects 或远程 wego.yaml 中声明的类型
+ * features 为要一并解析的功能组或可选依赖
+ */
+#[napi]
+pub async fn resolve_dependencies(
+  template_type: String,
+  name: String,
+  features: Option<Vec<String>>,
+) -> Vec<ResolvedTemplate> {
+  if helper::utils::read_config_file_from_local().is_err() {
+    return vec![];
+  }
+
+  match request::resolve::resolve_dependencies(&template_type, &name, &features.unwrap_or_default())
+    .await
+  {
+    Ok(templates) => templates,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      vec![]
+    }
+  }
+}
+
+/**
+ * 列出模板仓库的所有分支和 tag，可以作为 GenerateOptions 的 git_ref
+ */
+#[napi]
+pub async fn list_remote_refs() -> Vec<RemoteRef> {
+  if helper::utils::read_config_file_from_local().is_err() {
+    return vec![];
+  }
+
+  match request::refs::list_remote_refs().await {
+    Ok(refs) => refs,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      vec![]
+    }
+  }
+}
+
+/**
+ * 交互式选择模板仓库的分支或 tag，返回选择的 ref，取消时为空
+ */
+#[napi]
+pub async fn select_remote_ref() -> Option<String> {
+  if helper::utils::read_config_file_from_local().is_err() {
+    return None;
+  }
+
+  match request::refs::select_remote_ref().await {
+    Ok(git_ref) => git_ref,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      None
+    }
+  }
+}
+
+/**
+ * 对比 wego.lock 中记录的模板与远程模板，检查是否有更新
+ */
+#[napi]
+pub async fn check_outdated_templates() -> Vec<OutdatedTemplate> {
+  if helper::utils::read_config_file_from_local().is_ok() {
+    request::outdated::check_outdated_templates().await
+  } else {
+    vec![]
+  }
+}
+
+/**
+ * 升级已安装的模板，本地修改会与远程更新做三方合并，names 为空时升级全部模板
+ */
+#[napi]
+pub async fn upgrade_templates(names: Option<Vec<String>>) -> Vec<UpgradeResult> {
+  if helper::utils::read_config_file_from_local().is_ok() {
+    request::upgrade::upgrade_templates(names.unwrap_or_default()).await
+  } else {
+    vec![]
+  }
+}
+
+/**
+ * 对比本地模板与远程模板的差异，latest 为 true 时与升级会选择的版本对比，否则与安装时的版本对比
+ */
+#[napi]
+pub async fn diff_template(name: String, latest: Option<bool>) -> Vec<TemplateFileDiff> {
+  if helper::utils::read_config_file_from_local().is_err() {
+    return vec![];
+  }
+
+  match request::diff::diff_template(name, latest.unwrap_or(false)).await {
+    Ok(result) => result,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      vec![]
+    }
+  }
+}
+
+/**
+ * 列出已安装模板从安装时的提交到 git_ref 之间上游的提交和文件变化，用于升级前查看变更
+ * git_ref 为空时使用升级时会选择的版本
+ */
+#[napi]
+pub async fn template_changelog(
+  name: String,
+  git_ref: Option<String>,
+) -> Option<TemplateChangelog> {
+  if helper::utils::read_config_file_from_local().is_err() {
+    return None;
+  }
+
+  match request::changelog::template_changelog(name, git_ref).await {
+    Ok(changelog) => Some(changelog),
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      None
+    }
+  }
+}
+
+/**
+ * 删除已安装的模板，会检查其他模板是否依赖它，并可一并删除孤立的依赖
+ * template_type 可以是 components、pages、projects 或远程 wego.yaml 中声明的类型
+ * wego.lock 中没有记录的目录需要确认后才会删除，yes 为 true 时跳过确认
+ */
+#[napi]
+pub async fn remove_template(
+  template_type: String,
+  name: String,
+  yes: Option<bool>,
+) -> Vec<String> {
+  if helper::utils::read_config_file_from_local().is_err() {
+    return vec![];
+  }
+
+  match request::remove::remove_template(&template_type, name, yes.unwrap_or(false)).await {
+    Ok(removed) => removed,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      vec![]
+    }
   }
 }
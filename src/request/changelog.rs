@@ -0,0 +1,332 @@
+use colored::Colorize;
+use serde::Deserialize;
+
+use crate::helper::{lock::LockFile, utils::encode_query_value};
+
+use super::{
+  request::{get_config, request_github_api, resolve_revision},
+  version::resolve_upgrade_revision,
+};
+
+const COMMITS_PER_PAGE: usize = 100;
+/**
+ * compare API 最多返回 300 个变化的文件
+ */
+const COMPARE_FILES_LIMIT: usize = 300;
+
+/**
+ * 模板目录中的一次提交
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ChangelogCommit {
+  pub sha: String,
+  /**
+   * GitHub 用户名，提交邮箱未关联用户时为提交中的作者名称
+   */
+  pub author: String,
+  pub date: String,
+  pub message: String,
+}
+
+/**
+ * 模板目录中变化的文件，path 相对于模板目录
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ChangelogFile {
+  pub path: String,
+  /**
+   * added | modified | removed | renamed 等，与 GitHub compare API 相同
+   */
+  pub status: String,
+  pub additions: u32,
+  pub deletions: u32,
+}
+
+/**
+ * 已安装模板从安装时的提交到目标 ref 之间的变更
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TemplateChangelog {
+  pub name: String,
+  pub template_type: String,
+  pub path: String,
+  pub from_commit: String,
+  pub to_ref: String,
+  pub to_commit: String,
+  /**
+   * 从新到旧排列
+   */
+  pub commits: Vec<ChangelogCommit>,
+  pub files: Vec<ChangelogFile>,
+  /**
+   * 两次提交之间变化的文件超过 compare API 的上限，files 不完整
+   */
+  pub truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubCommit {
+  sha: String,
+  commit: GithubCommitDetail,
+  author: Option<GithubUser>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubCommitDetail {
+  author: GithubCommitAuthor,
+  committer: GithubCommitAuthor,
+  message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubCommitAuthor {
+  name: String,
+  date: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubUser {
+  login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubCompare {
+  #[serde(default)]
+  files: Vec<GithubCompareFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubCompareFile {
+  filename: String,
+  status: String,
+  additions: u32,
+  deletions: u32,
+}
+
+/**
+ * 列出已安装模板从安装时的提交到目标 ref 之间修改了模板目录的提交和文件
+ * git_ref 为空时使用升级时会选择的版本：固定的 ref、版本约束内的最新版本或 target_branch
+ */
+pub async fn template_changelog(
+  key: String,
+  git_ref: Option<String>,
+) -> Result<TemplateChangelog, String> {
+  let config = get_config();
+  let token = &config.github_api_token;
+  let lock_file = LockFile::read_from_local()?;
+  let template = lock_file
+    .find_by_name_or_path(&key)
+    .ok_or_else(|| format!("{} is not installed, there is no record in wego.lock", key))?;
+
+  if template.commit.is_empty() {
+    return Err(String::from(
+      "the installed commit is unknown, reinstall the template first",
+    ));
+  }
+
+  let revision = match git_ref {
+    Some(git_ref) => resolve_revision(token, &git_ref).await,
+    None => resolve_upgrade_revision(template).await?,
+  };
+  if revision.commit.is_empty() {
+    return Err(format!(
+      "can not resolve the commit of {}",
+      revision.git_ref
+    ));
+  }
+
+  let template_path = format!(
+    "{}/{}/{}",
+    config.templates_source,
+    template.get_source(),
+    template.name
+  );
+  let mut changelog = TemplateChangelog {
+    name: template.name.clone(),
+    template_type: template.template_type.clone(),
+    path: template.path.clone(),
+    from_commit: template.commit.clone(),
+    to_ref: revision.git_ref.clone(),
+    to_commit: revision.commit.clone(),
+    commits: vec![],
+    files: vec![],
+    truncated: false,
+  };
+
+  if template.commit != revision.commit {
+    let (commits, files) = futures::join!(
+      fetch_path_commits(token, &template_path, &template.commit, &revision.commit),
+      fetch_path_files(token, &template_path, &template.commit, &revision.commit)
+    );
+    changelog.commits = commits?;
+    (changelog.files, changelog.truncated) = files?;
+  }
+
+  print_changelog(&changelog);
+
+  Ok(changelog)
+}
+
+/**
+ * 通过 commits API 获取 to 上修改了 path 的提交，直到安装时的提交为止
+ * 安装时的提交不一定修改了 path，因此以它的提交时间作为 since，再排除它本身
+ */
+async fn fetch_path_commits(
+  token: &str,
+  path: &str,
+  from: &str,
+  to: &str,
+) -> Result<Vec<ChangelogCommit>, String> {
+  let config = get_config();
+  let body = request_github_api(&config.get_remote_commit_url(from), token, None).await?;
+  let since = serde_json::from_slice::<GithubCommit>(&body)
+    .map_err(|e| e.to_string())?
+    .commit
+    .committer
+    .date;
+
+  let mut commits = vec![];
+  let mut page = 1;
+
+  loop {
+    let uri = format!(
+      "{}?sha={}&path={}&since={}&per_page={}&page={}",
+      config.get_remote_commits_url(),
+      encode_query_value(to),
+      encode_query_value(path),
+      encode_query_value(&since),
+      COMMITS_PER_PAGE,
+      page
+    );
+    let body = request_github_api(&uri, token, None).await?;
+    let list = serde_json::from_slice::<Vec<GithubCommit>>(&body).map_err(|e| e.to_string())?;
+    let count = list.len();
+    let mut reached = false;
+
+    for item in list {
+      if item.sha == from {
+        reached = true;
+        break;
+      }
+      commits.push(ChangelogCommit {
+        sha: item.sha,
+        author: item
+          .author
+          .map(|user| user.login)
+          .unwrap_or(item.commit.author.name),
+        date: item.commit.author.date,
+        message: item.commit.message,
+      });
+    }
+
+    if reached || count < COMMITS_PER_PAGE {
+      break;
+    }
+    page += 1;
+  }
+
+  Ok(commits)
+}
+
+/**
+ * 通过 compare API 获取两个提交之间模板目录中变化的文件
+ */
+async fn fetch_path_files(
+  token: &str,
+  path: &str,
+  from: &str,
+  to: &str,
+) -> Result<(Vec<ChangelogFile>, bool), String> {
+  let uri = get_config().get_remote_compare_url(from, to);
+  let body = request_github_api(&uri, token, None).await?;
+  let compare = serde_json::from_slice::<GithubCompare>(&body).map_err(|e| e.to_string())?;
+  let truncated = compare.files.len() >= COMPARE_FILES_LIMIT;
+  let prefix = format!("{}/", path);
+  let files = compare
+    .files
+    .into_iter()
+    .filter_map(|file| {
+      Some(ChangelogFile {
+        path: file.filename.strip_prefix(&prefix)?.to_string(),
+        status: file.status,
+        additions: file.additions,
+        deletions: file.deletions,
+      })
+    })
+    .collect();
+
+  Ok((files, truncated))
+}
+
+fn print_changelog(changelog: &TemplateChangelog) {
+  println!(
+    "{} {} {} {} {} ({})",
+    "Changelog".bold(),
+    changelog.path.green(),
+    short_sha(&changelog.from_commit),
+    "---->".white(),
+    short_sha(&changelog.to_commit),
+    changelog.to_ref
+  );
+
+  if changelog.commits.is_empty() && changelog.files.is_empty() {
+    println!("{}", "No change upstream.".green());
+    return;
+  }
+
+  println!("{}", format!("{} commits:", changelog.commits.len()).bold());
+  for commit in &changelog.commits {
+    println!(
+      "  {} {} {} {}",
+      short_sha(&commit.sha).yellow(),
+      commit.date.get(..10).unwrap_or(&commit.date).white(),
+      commit.author.cyan(),
+      commit.message.lines().next().unwrap_or_default()
+    );
+  }
+
+  println!("{}", "Files:".bold());
+  for file in &changelog.files {
+    let status = match file.status.as_str() {
+      "added" => "A".green(),
+      "removed" => "D".red(),
+      "renamed" => "R".yellow(),
+      _ => "M".yellow(),
+    };
+    println!(
+      "  {} {} ({}, {})",
+      status,
+      file.path,
+      format!("+{}", file.additions).green(),
+      format!("-{}", file.deletions).red()
+    );
+  }
+
+  if changelog.truncated {
+    println!(
+      "{} {}",
+      "Warning:".yellow().bold(),
+      format!(
+        "more than {} files changed upstream, the file list may be incomplete",
+        COMPARE_FILES_LIMIT
+      )
+      .yellow()
+    );
+  }
+
+  let additions: u32 = changelog.files.iter().map(|file| file.additions).sum();
+  let deletions: u32 = changelog.files.iter().map(|file| file.deletions).sum();
+  println!(
+    "{} files changed, {} insertions(+), {} deletions(-)",
+    changelog.files.len(),
+    additions,
+    deletions
+  );
+}
+
+fn short_sha(sha: &str) -> &str {
+  sha.get(..7).unwrap_or(sha)
+}
@@ -0,0 +1,165 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use colored::Colorize;
+use diffy::{DiffOptions, PatchFormatter};
+
+use crate::{
+  helper::lock::{read_local_dir, to_relative_path, LockFile},
+  TemplateType,
+};
+
+use super::{
+  request::{fetch_template_files, get_config, get_local_dir_path},
+  version::resolve_upgrade_revision,
+};
+
+pub const DIFF_MODIFIED: &str = "modified";
+pub const DIFF_ADDED: &str = "added";
+pub const DIFF_REMOVED: &str = "removed";
+
+/**
+ * 本地模板文件与远程模板的差异
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TemplateFileDiff {
+  pub path: String,
+  /**
+   * modified | added | removed，added 表示仅本地存在，removed 表示仅远程存在
+   */
+  pub status: String,
+  pub patch: String,
+}
+
+/**
+ * 对比本地模板目录与远程模板，latest 为 false 时对比安装时的版本，为 true 时对比升级会选择的版本
+ */
+pub async fn diff_template(key: String, latest: bool) -> Result<Vec<TemplateFileDiff>, String> {
+  let config = get_config();
+  let lock_file = LockFile::read_from_local()?;
+
+  let (source, name, local_path, git_ref) = match lock_file.find_by_name_or_path(&key) {
+    Some(template) => {
+      // 与最新版本对比时使用升级会选择的版本，固定了 ref 或有版本约束的模板不与 target_branch 对比
+      let git_ref = if latest || template.commit.is_empty() {
+        resolve_upgrade_revision(template)
+          .await?
+          .fetch_ref()
+          .to_string()
+      } else {
+        template.commit.clone()
+      };
+
+      (
+        template.get_source().to_string(),
+        template.name.clone(),
+        template.get_local_path(),
+        git_ref,
+      )
+    }
+    None => {
+      // 没有安装记录时按组件处理，与远程最新版本对比
+      let type_name = TemplateType::Components.dir_name();
+      let local_path = config.get_local_dir(type_name, &key, &key, None, None)?;
+
+      (
+        type_name.to_string(),
+        key.clone(),
+        local_path,
+        config.target_branch.clone(),
+      )
+    }
+  };
+
+  let url = config.get_remote_template_url(&source, &name);
+  let upstream = fetch_template_files(url, &config.github_api_token, &git_ref).await?;
+  let local = read_local_dir(&local_path);
+
+  println!(
+    "{} {} {} {}@{}",
+    "Diff".bold(),
+    to_relative_path(&get_local_dir_path(), &local_path).green(),
+    "<---->".white(),
+    name.green(),
+    git_ref
+  );
+
+  let result = diff_files(&upstream, &local);
+  print_template_diffs(&result);
+
+  Ok(result)
+}
+
+fn diff_files(
+  upstream: &BTreeMap<String, Vec<u8>>,
+  local: &BTreeMap<String, Vec<u8>>,
+) -> Vec<TemplateFileDiff> {
+  let paths: BTreeSet<&String> = upstream.keys().chain(local.keys()).collect();
+  let mut result = vec![];
+
+  for path in paths {
+    let status = match (upstream.get(path), local.get(path)) {
+      (Some(original), Some(modified)) if original != modified => DIFF_MODIFIED,
+      (Some(_), None) => DIFF_REMOVED,
+      (None, Some(_)) => DIFF_ADDED,
+      _ => continue,
+    };
+
+    let original = upstream
+      .get(path)
+      .map(|item| item.as_slice())
+      .unwrap_or_default();
+    let modified = local
+      .get(path)
+      .map(|item| item.as_slice())
+      .unwrap_or_default();
+
+    let patch = match (std::str::from_utf8(original), std::str::from_utf8(modified)) {
+      (Ok(original), Ok(modified)) => DiffOptions::new()
+        .set_original_filename(format!("a/{}", path))
+        .set_modified_filename(format!("b/{}", path))
+        .create_patch(original, modified)
+        .to_string(),
+      _ => format!("Binary files a/{} and b/{} differ\n", path, path),
+    };
+
+    result.push(TemplateFileDiff {
+      path: path.clone(),
+      status: status.to_string(),
+      patch,
+    });
+  }
+
+  result
+}
+
+fn print_template_diffs(list: &[TemplateFileDiff]) {
+  if list.is_empty() {
+    println!("{}", "No difference with the remote template!".green());
+    return;
+  }
+
+  let formatter = PatchFormatter::new().with_color();
+
+  for item in list {
+    let status = match item.status.as_str() {
+      DIFF_ADDED => "added".green().bold(),
+      DIFF_REMOVED => "removed".red().bold(),
+      _ => "modified".yellow().bold(),
+    };
+    println!("{} {}", status, item.path);
+
+    match diffy::Patch::from_str(&item.patch) {
+      Ok(patch) => print!("{}", formatter.fmt_patch(&patch)),
+      Err(_) => print!("{}", item.patch),
+    }
+  }
+
+  let count = |status: &str| list.iter().filter(|item| item.status == status).count();
+  println!(
+    "{} modified, {} added, {} removed",
+    count(DIFF_MODIFIED).to_string().yellow(),
+    count(DIFF_ADDED).to_string().green(),
+    count(DIFF_REMOVED).to_string().red()
+  );
+}
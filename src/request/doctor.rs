@@ -0,0 +1,728 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use colored::Colorize;
+use serde::Deserialize;
+use tokio::{
+  io::{AsyncReadExt, AsyncWriteExt},
+  net::TcpStream,
+  time::timeout,
+};
+
+use crate::helper::{
+  config::{
+    apply_config, resolve_config, CONFIG_FILE_NAME, KEY_GITHUB_API_TOKEN, KEY_GITHUB_NAME,
+    KEY_REPO_NAME,
+  },
+  credential::get_api_host,
+  utils::encode_query_value,
+  ConfigFile,
+};
+
+use super::{
+  request::{fetch_template_tree_shas, request_github_api_with_headers},
+  schema::parse_remote_yaml,
+  ContentsResponse, RemoteYaml,
+};
+
+pub const CHECK_PASS: &str = "pass";
+pub const CHECK_WARN: &str = "warn";
+pub const CHECK_FAIL: &str = "fail";
+pub const CHECK_SKIP: &str = "skip";
+
+/**
+ * 生成一个模板大约需要的请求数，剩余额度低于该值时给出警告
+ */
+const RATE_LIMIT_WARNING: u32 = 100;
+const PROXY_TIMEOUT: Duration = Duration::from_secs(10);
+const PROXY_ENV_NAMES: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+const NO_PROXY_ENV_NAMES: [&str; 2] = ["NO_PROXY", "no_proxy"];
+
+/**
+ * doctor 的一项检查结果
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct DoctorCheck {
+  pub name: String,
+  /**
+   * pass | warn | fail | skip
+   */
+  pub status: String,
+  pub message: String,
+  /**
+   * 检查未通过时建议的修复方式
+   */
+  pub fix: Option<String>,
+}
+
+impl DoctorCheck {
+  fn new(name: &str, status: &str, message: String, fix: Option<String>) -> Self {
+    DoctorCheck {
+      name: name.to_string(),
+      status: status.to_string(),
+      message,
+      fix,
+    }
+  }
+
+  fn pass(name: &str, message: String) -> Self {
+    DoctorCheck::new(name, CHECK_PASS, message, None)
+  }
+
+  fn warn(name: &str, message: String, fix: String) -> Self {
+    DoctorCheck::new(name, CHECK_WARN, message, Some(fix))
+  }
+
+  fn fail(name: &str, message: String, fix: String) -> Self {
+    DoctorCheck::new(name, CHECK_FAIL, message, Some(fix))
+  }
+
+  fn skip(name: &str, reason: &str) -> Self {
+    DoctorCheck::new(name, CHECK_SKIP, reason.to_string(), None)
+  }
+
+  fn is_fail(&self) -> bool {
+    self.status == CHECK_FAIL
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubUser {
+  login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubRepo {
+  default_branch: String,
+  #[serde(default)]
+  private: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitResponse {
+  resources: RateLimitResources,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitResources {
+  core: RateLimit,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimit {
+  limit: u32,
+  remaining: u32,
+  reset: u64,
+}
+
+const CHECK_CONFIG: &str = "config";
+const CHECK_API_HOST: &str = "api host";
+const CHECK_PROXY: &str = "proxy";
+const CHECK_TOKEN: &str = "token";
+const CHECK_RATE_LIMIT: &str = "rate limit";
+const CHECK_BRANCH: &str = "branch";
+const CHECK_REMOTE_YAML: &str = "remote wego.yaml";
+const CHECK_TEMPLATES: &str = "template folders";
+
+/**
+ * 依次检查配置、网络、token、限流额度、分支、远程 wego.yaml 和模板目录
+ * 前置检查失败时后面依赖它的检查会被跳过
+ */
+pub async fn run_doctor() -> Vec<DoctorCheck> {
+  let mut checks = vec![];
+
+  let config = check_config(&mut checks);
+
+  let reachable = match &config {
+    Some(config) => check_api_host(config, &mut checks).await,
+    None => {
+      checks.push(DoctorCheck::skip(CHECK_API_HOST, "config is invalid"));
+      false
+    }
+  };
+
+  let repo = match (&config, reachable) {
+    (Some(config), true) => check_token(config, &mut checks).await,
+    _ => {
+      checks.push(DoctorCheck::skip(CHECK_TOKEN, "api host is unreachable"));
+      checks.push(DoctorCheck::skip(
+        CHECK_RATE_LIMIT,
+        "api host is unreachable",
+      ));
+      None
+    }
+  };
+
+  let branch_exists = match (&config, &repo) {
+    (Some(config), Some(repo)) => check_branch(config, repo, &mut checks).await,
+    _ => {
+      checks.push(DoctorCheck::skip(CHECK_BRANCH, "repo is not accessible"));
+      false
+    }
+  };
+
+  let manifest = match (&config, branch_exists) {
+    (Some(config), true) => check_remote_yaml(config, &mut checks).await,
+    _ => {
+      checks.push(DoctorCheck::skip(CHECK_REMOTE_YAML, "branch is not found"));
+      None
+    }
+  };
+
+  match (&config, &manifest) {
+    (Some(config), Some(manifest)) => check_template_folders(config, manifest, &mut checks).await,
+    _ => checks.push(DoctorCheck::skip(
+      CHECK_TEMPLATES,
+      "remote wego.yaml is not available",
+    )),
+  }
+
+  print_doctor_checks(&checks);
+
+  checks
+}
+
+fn check_config(checks: &mut Vec<DoctorCheck>) -> Option<ConfigFile> {
+  let resolved = match resolve_config() {
+    Ok(resolved) => resolved,
+    Err(e) => {
+      checks.push(DoctorCheck::fail(
+        CHECK_CONFIG,
+        format!("config can not be parsed: {}", e),
+        format!("Fix the yaml syntax of {}", CONFIG_FILE_NAME),
+      ));
+      return None;
+    }
+  };
+
+  let missing: Vec<&str> = [KEY_GITHUB_NAME, KEY_REPO_NAME]
+    .into_iter()
+    .filter(|key| resolved.get(key).is_none())
+    .collect();
+
+  if !missing.is_empty() {
+    let fix = match &resolved.project_file {
+      Some(file) => format!("Add {} to {}", missing.join(", "), file.display()),
+      None => String::from("Run init to create a wego.yaml in your project"),
+    };
+    checks.push(DoctorCheck::fail(
+      CHECK_CONFIG,
+      format!("missing required config: {}", missing.join(", ")),
+      fix,
+    ));
+    return None;
+  }
+
+  apply_config(&resolved);
+
+  let location = match &resolved.project_file {
+    Some(file) => file.display().to_string(),
+    None => String::from("user config and environment"),
+  };
+  checks.push(DoctorCheck::pass(
+    CHECK_CONFIG,
+    format!("loaded from {}", location),
+  ));
+
+  Some(resolved.to_config_file())
+}
+
+/**
+ * 匿名请求 /rate_limit 检查 API 主机是否可以访问，该接口不消耗限流额度
+ */
+async fn check_api_host(config: &ConfigFile, checks: &mut Vec<DoctorCheck>) -> bool {
+  let host = get_api_host(&config.get_api_base());
+  let proxy = get_proxy_for_host(&host);
+
+  if let Some(proxy) = &proxy {
+    checks.push(check_proxy_tunnel(proxy, &host).await);
+  }
+
+  let uri = format!("{}/rate_limit", config.get_api_root());
+  match request_github_api_with_headers(&uri, "", None).await {
+    Ok((status, _, _)) => {
+      checks.push(DoctorCheck::pass(
+        CHECK_API_HOST,
+        format!("{} is reachable, code: {}", host, status),
+      ));
+      true
+    }
+    Err(e) => {
+      let fix = match &proxy {
+        Some(proxy) => format!(
+          "Requests are sent to {} directly and do not go through the proxy {}, allow direct access to it or set api_base to a reachable host",
+          host, proxy
+        ),
+        None => format!(
+          "Check your network and DNS, or set api_base if you use Github Enterprise, current: {}",
+          config.get_api_base()
+        ),
+      };
+      checks.push(DoctorCheck::fail(
+        CHECK_API_HOST,
+        format!("{} is unreachable: {}", host, e),
+        fix,
+      ));
+      false
+    }
+  }
+}
+
+/**
+ * 环境变量中配置的代理地址，NO_PROXY 中包含 host 时返回 None
+ */
+fn get_proxy_for_host(host: &str) -> Option<String> {
+  let no_proxy = NO_PROXY_ENV_NAMES
+    .iter()
+    .find_map(|name| std::env::var(name).ok())
+    .unwrap_or_default();
+
+  let excluded = no_proxy
+    .split(',')
+    .map(|item| item.trim().trim_start_matches('.'))
+    .filter(|item| !item.is_empty())
+    .any(|item| item == "*" || host == item || host.ends_with(&format!(".{}", item)));
+  if excluded {
+    return None;
+  }
+
+  PROXY_ENV_NAMES
+    .iter()
+    .find_map(|name| std::env::var(name).ok())
+    .filter(|proxy| !proxy.is_empty())
+}
+
+/**
+ * 通过代理的 CONNECT 建立到 API 主机的隧道，检查代理本身是否可用
+ * wego 的请求不经过代理，隧道可用时也只给出警告，提示 API 主机需要能直接访问
+ */
+async fn check_proxy_tunnel(proxy: &str, host: &str) -> DoctorCheck {
+  let address = proxy
+    .split("://")
+    .last()
+    .unwrap_or_default()
+    .rsplit('@')
+    .next()
+    .unwrap_or_default()
+    .trim_end_matches('/');
+  let address = if address.contains(':') {
+    address.to_string()
+  } else {
+    format!("{}:80", address)
+  };
+
+  let tunnel = async {
+    let mut stream = TcpStream::connect(&address)
+      .await
+      .map_err(|e| e.to_string())?;
+    stream
+      .write_all(format!("CONNECT {0}:443 HTTP/1.1\r\nHost: {0}:443\r\n\r\n", host).as_bytes())
+      .await
+      .map_err(|e| e.to_string())?;
+
+    let mut buffer = [0; 512];
+    let size = stream.read(&mut buffer).await.map_err(|e| e.to_string())?;
+    let response = String::from_utf8_lossy(&buffer[..size]).to_string();
+
+    Ok::<String, String>(response.lines().next().unwrap_or_default().to_string())
+  };
+  let result = match timeout(PROXY_TIMEOUT, tunnel).await {
+    Ok(result) => result,
+    Err(_) => Err(format!("timed out after {}s", PROXY_TIMEOUT.as_secs())),
+  };
+
+  match result {
+    Ok(status_line) if status_line.split_whitespace().nth(1) == Some("200") => DoctorCheck::warn(
+      CHECK_PROXY,
+      format!(
+        "{} can tunnel to {}, but wego does not use proxies and connects to {} directly",
+        proxy, host, host
+      ),
+      format!(
+        "Make sure {} is reachable without the proxy, or set api_base to a host that is",
+        host
+      ),
+    ),
+    Ok(status_line) => DoctorCheck::fail(
+      CHECK_PROXY,
+      format!("{} refused to tunnel to {}: {}", proxy, host, status_line),
+      String::from("Check the proxy credentials and whether the proxy allows CONNECT to port 443"),
+    ),
+    Err(e) => DoctorCheck::fail(
+      CHECK_PROXY,
+      format!("{} is unreachable: {}", proxy, e),
+      String::from("Check HTTPS_PROXY/ALL_PROXY, or add the api host to NO_PROXY"),
+    ),
+  }
+}
+
+/**
+ * 校验 token 并检查能否访问仓库，classic token 访问私有仓库需要 repo 权限
+ * 同时检查 token 对应的限流额度
+ */
+async fn check_token(config: &ConfigFile, checks: &mut Vec<DoctorCheck>) -> Option<GithubRepo> {
+  let token = &config.github_api_token;
+  let mut scopes: Option<Vec<String>> = None;
+
+  if token.is_empty() {
+    checks.push(DoctorCheck::warn(
+      CHECK_TOKEN,
+      String::from("no token, only public repos can be accessed"),
+      format!(
+        "Set {} in wego.local.yaml, or GITHUB_TOKEN/WEGO_TOKEN in the environment",
+        KEY_GITHUB_API_TOKEN
+      ),
+    ));
+  } else {
+    match request_github_api_with_headers(&config.get_user_url(), token, None).await {
+      Ok((401, _, _)) => {
+        checks.push(DoctorCheck::fail(
+          CHECK_TOKEN,
+          String::from("token is invalid or expired"),
+          String::from("Create a new token, show config tells where the current token comes from"),
+        ));
+        checks.push(DoctorCheck::skip(CHECK_RATE_LIMIT, "token is invalid"));
+        return None;
+      }
+      Ok((200, headers, body)) => {
+        scopes = headers
+          .get("x-oauth-scopes")
+          .and_then(|value| value.to_str().ok())
+          .map(|value| {
+            value
+              .split(',')
+              .map(|item| item.trim().to_string())
+              .filter(|item| !item.is_empty())
+              .collect()
+          });
+        let login = serde_json::from_slice::<GithubUser>(&body)
+          .map(|user| user.login)
+          .unwrap_or_else(|_| String::from("unknown user"));
+
+        checks.push(DoctorCheck::pass(
+          CHECK_TOKEN,
+          match &scopes {
+            Some(scopes) => format!(
+              "authenticated as {}, scopes: [{}]",
+              login,
+              scopes.join(", ")
+            ),
+            None => format!("authenticated as {}, fine-grained token", login),
+          },
+        ));
+      }
+      Ok((403, _, _)) => {
+        // fine-grained token 没有读取用户信息的权限，或者被限流，仓库检查会给出最终结果
+        checks.push(DoctorCheck::warn(
+          CHECK_TOKEN,
+          String::from("token can not read the user, code 403"),
+          String::from(
+            "Grant the token read access to the user profile, or check the rate limit below",
+          ),
+        ));
+      }
+      Ok((status, _, _)) => {
+        checks.push(DoctorCheck::fail(
+          CHECK_TOKEN,
+          format!("token can not be verified, code {}", status),
+          if status >= 500 {
+            String::from("Github is unavailable, retry later")
+          } else {
+            String::from("Check api_base, it should point to the Github REST API")
+          },
+        ));
+      }
+      Err(e) => {
+        checks.push(DoctorCheck::fail(
+          CHECK_TOKEN,
+          format!("token can not be verified: {}", e),
+          String::from("Retry later or check your network"),
+        ));
+      }
+    }
+  }
+
+  checks.push(check_rate_limit(config).await);
+
+  match request_github_api_with_headers(&config.get_repo_url(), token, None).await {
+    Ok((200, _, body)) => {
+      let repo = match serde_json::from_slice::<GithubRepo>(&body) {
+        Ok(repo) => repo,
+        Err(e) => {
+          checks.push(DoctorCheck::fail(
+            CHECK_TOKEN,
+            format!(
+              "{} returned an unexpected response: {}",
+              config.get_repo_full_name(),
+              e
+            ),
+            String::from("Check api_base, it should point to the Github REST API"),
+          ));
+          return None;
+        }
+      };
+      let lacks_repo_scope = repo.private
+        && scopes
+          .as_ref()
+          .map(|scopes| !scopes.iter().any(|scope| scope == "repo"))
+          .unwrap_or(false);
+
+      if lacks_repo_scope {
+        checks.push(DoctorCheck::warn(
+          CHECK_TOKEN,
+          format!(
+            "{} is private but the token has no repo scope",
+            config.get_repo_full_name()
+          ),
+          String::from("Grant the repo scope to the token"),
+        ));
+      }
+      Some(repo)
+    }
+    Ok((status, _, _)) => {
+      let fix = if token.is_empty() {
+        String::from("The repo may be private, set a token that can read it")
+      } else if scopes.is_some() {
+        String::from("Check github_name and repo_name, and grant the repo scope to the token for private repos")
+      } else {
+        String::from("Check github_name and repo_name, and grant Contents read access of the repo to the token")
+      };
+      checks.push(DoctorCheck::fail(
+        CHECK_TOKEN,
+        format!(
+          "{} is not accessible, code: {}",
+          config.get_repo_full_name(),
+          status
+        ),
+        fix,
+      ));
+      None
+    }
+    Err(e) => {
+      checks.push(DoctorCheck::fail(
+        CHECK_TOKEN,
+        format!("{} is not accessible: {}", config.get_repo_full_name(), e),
+        String::from("Retry later or check your network"),
+      ));
+      None
+    }
+  }
+}
+
+async fn check_rate_limit(config: &ConfigFile) -> DoctorCheck {
+  let uri = format!("{}/rate_limit", config.get_api_root());
+  let rate_limit = match request_github_api_with_headers(&uri, &config.github_api_token, None).await
+  {
+    Ok((200, _, body)) => serde_json::from_slice::<RateLimitResponse>(&body)
+      .map(|response| response.resources.core)
+      .map_err(|e| e.to_string()),
+    Ok((status, _, _)) => Err(format!("code: {}", status)),
+    Err(e) => Err(e),
+  };
+
+  let rate_limit = match rate_limit {
+    Ok(rate_limit) => rate_limit,
+    Err(e) => {
+      return DoctorCheck::warn(
+        CHECK_RATE_LIMIT,
+        format!("rate limit is unknown, {}", e),
+        String::from("Github Enterprise may have rate limit disabled, nothing to do in that case"),
+      )
+    }
+  };
+
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs())
+    .unwrap_or_default();
+  let minutes = rate_limit.reset.saturating_sub(now).div_ceil(60);
+  let message = format!(
+    "{}/{} requests remaining, resets in {} minutes",
+    rate_limit.remaining, rate_limit.limit, minutes
+  );
+  let fix = if config.github_api_token.is_empty() {
+    String::from("Set a token to raise the limit to 5000 requests per hour")
+  } else {
+    format!("Wait {} minutes for the limit to reset", minutes)
+  };
+
+  if rate_limit.remaining == 0 {
+    DoctorCheck::fail(CHECK_RATE_LIMIT, message, fix)
+  } else if rate_limit.remaining < RATE_LIMIT_WARNING {
+    DoctorCheck::warn(CHECK_RATE_LIMIT, message, fix)
+  } else {
+    DoctorCheck::pass(CHECK_RATE_LIMIT, message)
+  }
+}
+
+async fn check_branch(
+  config: &ConfigFile,
+  repo: &GithubRepo,
+  checks: &mut Vec<DoctorCheck>,
+) -> bool {
+  let uri = format!(
+    "{}/{}",
+    config.get_remote_branches_url(),
+    config.target_branch
+  );
+
+  match request_github_api_with_headers(&uri, &config.github_api_token, None).await {
+    Ok((200, _, _)) => {
+      checks.push(DoctorCheck::pass(
+        CHECK_BRANCH,
+        format!("{} exists", config.target_branch),
+      ));
+      true
+    }
+    Ok((status, _, _)) => {
+      checks.push(DoctorCheck::fail(
+        CHECK_BRANCH,
+        format!("{} is not found, code: {}", config.target_branch, status),
+        format!(
+          "Set target_branch to an existing branch, the default branch is {}",
+          repo.default_branch
+        ),
+      ));
+      false
+    }
+    Err(e) => {
+      checks.push(DoctorCheck::fail(
+        CHECK_BRANCH,
+        format!("{} can not be checked: {}", config.target_branch, e),
+        String::from("Retry later or check your network"),
+      ));
+      false
+    }
+  }
+}
+
+async fn check_remote_yaml(
+  config: &ConfigFile,
+  checks: &mut Vec<DoctorCheck>,
+) -> Option<RemoteYaml> {
+  let uri = format!(
+    "{}?ref={}",
+    config.get_remote_yaml_url(),
+    encode_query_value(&config.target_branch)
+  );
+
+  let content = match request_github_api_with_headers(&uri, &config.github_api_token, None).await {
+    Ok((200, _, body)) => serde_json::from_slice::<ContentsResponse>(&body)
+      .map_err(|e| e.to_string())
+      .and_then(|content| content.decode_base64_to_string().map_err(|e| e.to_string())),
+    Ok((status, _, _)) => {
+      checks.push(DoctorCheck::fail(
+        CHECK_REMOTE_YAML,
+        format!(
+          "there is no wego.yaml on {}, code: {}",
+          config.target_branch, status
+        ),
+        format!(
+          "Add a wego.yaml to the root of {}",
+          config.get_repo_full_name()
+        ),
+      ));
+      return None;
+    }
+    Err(e) => Err(e),
+  };
+
+  let result = content.and_then(|content| parse_remote_yaml(&content));
+
+  match result {
+    Ok(manifest) => {
+      let count: usize = manifest
+        .get_types()
+        .iter()
+        .map(|item| item.templates.len())
+        .sum();
+      checks.push(DoctorCheck::pass(
+        CHECK_REMOTE_YAML,
+        format!("parsed, {} templates", count),
+      ));
+      Some(manifest)
+    }
+    Err(e) => {
+      checks.push(DoctorCheck::fail(
+        CHECK_REMOTE_YAML,
+        format!("can not be parsed: {}", e),
+        String::from("Fix the remote wego.yaml according to the error position"),
+      ));
+      None
+    }
+  }
+}
+
+/**
+ * 检查 wego.yaml 中声明的每个模板在 templates_source 下都有对应目录
+ */
+async fn check_template_folders(
+  config: &ConfigFile,
+  manifest: &RemoteYaml,
+  checks: &mut Vec<DoctorCheck>,
+) {
+  let mut missing = vec![];
+  let mut count = 0;
+
+  for template_type in manifest.get_types() {
+    if template_type.templates.is_empty() {
+      continue;
+    }
+
+    let source = template_type.get_source();
+    let dirs = fetch_template_tree_shas(&source, &config.github_api_token, &config.target_branch)
+      .await
+      .unwrap_or_default();
+
+    for template in &template_type.templates {
+      count += 1;
+      if !dirs.contains_key(&template.name) {
+        missing.push(format!(
+          "{}/{}/{}",
+          config.templates_source, source, template.name
+        ));
+      }
+    }
+  }
+
+  if missing.is_empty() {
+    checks.push(DoctorCheck::pass(
+      CHECK_TEMPLATES,
+      format!("all {} template folders exist", count),
+    ));
+  } else {
+    checks.push(DoctorCheck::fail(
+      CHECK_TEMPLATES,
+      format!("missing folders: {}", missing.join(", ")),
+      format!(
+        "Add the folders to {} on {}, or remove the entries from wego.yaml",
+        config.get_repo_full_name(),
+        config.target_branch
+      ),
+    ));
+  }
+}
+
+fn print_doctor_checks(checks: &[DoctorCheck]) {
+  for check in checks {
+    let status = format!("[{}]", check.status);
+    let status = match check.status.as_str() {
+      CHECK_PASS => status.green(),
+      CHECK_WARN => status.yellow(),
+      CHECK_FAIL => status.red(),
+      _ => status.white(),
+    };
+    println!("{} {} ----> {}", status, check.name.bold(), check.message);
+
+    if let Some(fix) = &check.fix {
+      println!("       {} {}", "fix:".cyan(), fix);
+    }
+  }
+
+  let failed = checks.iter().filter(|check| check.is_fail()).count();
+  if failed == 0 {
+    println!("{}", "No problem found!".green());
+  } else {
+    println!("{}", format!("{} problems found!", failed).red());
+  }
+}
@@ -0,0 +1,378 @@
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, Password, Select};
+use serde::Deserialize;
+
+use crate::helper::{
+  config::{resolve_config, ConfigValue, KEY_GITHUB_API_TOKEN},
+  utils::{
+    confirm_overwrite, encode_query_value, get_local_config_file_path_string, write_init_config,
+  },
+  ConfigFile, LocalConfigFile,
+};
+
+use super::{
+  refs::fetch_remote_branches, request::request_github_api_with_status, schema::parse_remote_yaml,
+  ContentsResponse, RemoteGithubDir,
+};
+
+const DEFAULT_TEMPLATES_SOURCE: &str = "templates";
+const DEFAULT_TARGET_BRANCH: &str = "main";
+
+#[derive(Debug, Deserialize)]
+struct GithubUser {
+  login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubRepo {
+  full_name: String,
+  default_branch: String,
+  #[serde(default)]
+  private: bool,
+}
+
+/**
+ * 交互式初始化 wego.yaml，输入过程中通过 API 校验 token 和仓库，
+ * 分支和 templates_source 从仓库中实际存在的分支和目录中选择，保存前确认远程 wego.yaml 存在
+ */
+pub async fn init_config_with_stdin(force: bool) -> Result<(), String> {
+  if !confirm_overwrite(&get_local_config_file_path_string(), force) {
+    return Ok(());
+  }
+
+  let (mut config, token_source) = match resolve_config() {
+    Ok(resolved) => (
+      resolved.to_config_file(),
+      resolved
+        .values
+        .get(KEY_GITHUB_API_TOKEN)
+        .filter(|item| !item.value.is_empty())
+        .cloned(),
+    ),
+    Err(_) => (
+      ConfigFile::new(
+        String::new(),
+        String::new(),
+        String::new(),
+        String::from(DEFAULT_TARGET_BRANCH),
+        String::from(DEFAULT_TEMPLATES_SOURCE),
+      ),
+      None,
+    ),
+  };
+
+  let github_api_token = input_github_api_token(&mut config, token_source).await?;
+  let repo = input_repo(&mut config).await?;
+
+  config.target_branch = select_branch(&config, &repo.default_branch).await?;
+  config.templates_source = select_templates_source(&config).await?;
+
+  if !check_remote_yaml(&config).await? {
+    let save = Confirm::with_theme(&ColorfulTheme::default())
+      .with_prompt("Save the config anyway?")
+      .default(false)
+      .interact()
+      .map_err(|e| e.to_string())?;
+
+    if !save {
+      println!("{}", "Init canceled, nothing is written.".yellow());
+      return Ok(());
+    }
+  }
+
+  let config_file = LocalConfigFile {
+    github_name: Some(config.github_name.clone()),
+    repo_name: Some(config.repo_name.clone()),
+    templates_source: Some(config.templates_source.clone())
+      .filter(|item| item != DEFAULT_TEMPLATES_SOURCE),
+    target_branch: Some(config.target_branch.clone()).filter(|item| item != DEFAULT_TARGET_BRANCH),
+    ..Default::default()
+  };
+
+  write_init_config(&config_file, &github_api_token)
+}
+
+/**
+ * 输入 token 并调用 /user 校验，已有其他来源的 token 时可以留空沿用
+ * 返回需要写入 wego.local.yaml 的 token，沿用已有 token 时为空，
+ * 但沿用的 token 来自即将被覆盖的 wego.yaml 时需要写入 wego.local.yaml，否则会丢失
+ */
+async fn input_github_api_token(
+  config: &mut ConfigFile,
+  token_source: Option<ConfigValue>,
+) -> Result<String, String> {
+  let prompt = match &token_source {
+    Some(source) => format!(
+      "Github Api Token(leave empty to use the token from {})",
+      source.source
+    ),
+    None => String::from("Github Api Token(leave empty for public repos)"),
+  };
+  let overwritten = token_source
+    .as_ref()
+    .and_then(|source| source.location.as_deref())
+    .map(|location| location.ends_with(&get_local_config_file_path_string()))
+    .unwrap_or(false);
+
+  loop {
+    let input: String = Password::new()
+      .with_prompt(&prompt)
+      .allow_empty_password(true)
+      .interact()
+      .map_err(|e| e.to_string())?;
+
+    let token = if input.is_empty() {
+      config.github_api_token.clone()
+    } else {
+      input.clone()
+    };
+    let local_token = if input.is_empty() && overwritten {
+      token.clone()
+    } else {
+      input
+    };
+
+    if token.is_empty() {
+      println!(
+        "{}",
+        "Without a token only public repos can be used, and the api rate limit is low.".yellow()
+      );
+      return Ok(local_token);
+    }
+
+    let (status, body) =
+      request_github_api_with_status(&config.get_user_url(), &token, None).await?;
+    match status {
+      200 => {
+        if let Ok(user) = serde_json::from_slice::<GithubUser>(&body) {
+          println!(
+            "{} {}",
+            "Token is valid, login as".green(),
+            user.login.green()
+          );
+        }
+        config.github_api_token = token;
+        return Ok(local_token);
+      }
+      401 => println!(
+        "{} {}",
+        "Error:".red().bold(),
+        "The token is invalid or expired, please input again.".red()
+      ),
+      _ => {
+        // fine-grained token 等没有读取用户信息的权限，交给后续的仓库校验
+        config.github_api_token = token;
+        return Ok(local_token);
+      }
+    }
+  }
+}
+
+/**
+ * 输入 github_name 和 repo_name，仓库不存在或 token 无权访问时重新输入
+ */
+async fn input_repo(config: &mut ConfigFile) -> Result<GithubRepo, String> {
+  loop {
+    config.github_name = input_with_default("Github Name", &config.github_name)?;
+    config.repo_name = input_with_default("Repo Name", &config.repo_name)?;
+
+    let (status, body) =
+      request_github_api_with_status(&config.get_repo_url(), &config.github_api_token, None)
+        .await?;
+
+    match status {
+      200 => {
+        let repo = serde_json::from_slice::<GithubRepo>(&body).map_err(|e| e.to_string())?;
+        println!(
+          "{} {}{}",
+          "Found repo".green(),
+          repo.full_name.green(),
+          if repo.private { " (private)" } else { "" }
+        );
+        return Ok(repo);
+      }
+      401 => println!(
+        "{} {}",
+        "Error:".red().bold(),
+        "The token is invalid or expired.".red()
+      ),
+      403 => println!(
+        "{} {}",
+        "Error:".red().bold(),
+        "Access forbidden, the api rate limit may be exceeded or the token lacks permission.".red()
+      ),
+      404 => println!(
+        "{} {}",
+        "Error:".red().bold(),
+        format!(
+          "Repo {} is not found, or the token can not access it.",
+          config.get_repo_full_name()
+        )
+        .red()
+      ),
+      _ => println!(
+        "{} {}",
+        "Error:".red().bold(),
+        format!(
+          "Request {} failure, code: {}",
+          config.get_repo_url(),
+          status
+        )
+        .red()
+      ),
+    }
+  }
+}
+
+fn input_with_default(prompt: &str, default: &str) -> Result<String, String> {
+  let mut input = Input::<String>::new();
+  input.with_prompt(prompt);
+  if !default.is_empty() {
+    input.default(default.to_string());
+  }
+
+  input.interact_text().map_err(|e| e.to_string())
+}
+
+/**
+ * 从仓库的实际分支中选择，默认选中仓库的默认分支
+ */
+async fn select_branch(config: &ConfigFile, default_branch: &str) -> Result<String, String> {
+  let branches: Vec<String> = fetch_remote_branches(config)
+    .await?
+    .into_iter()
+    .map(|item| item.name)
+    .collect();
+
+  if branches.is_empty() {
+    return Err(format!(
+      "There is no branch in {}!",
+      config.get_repo_full_name()
+    ));
+  }
+
+  let default = branches
+    .iter()
+    .position(|item| item == &config.target_branch)
+    .or_else(|| branches.iter().position(|item| item == default_branch))
+    .unwrap_or(0);
+
+  let index = Select::with_theme(&ColorfulTheme::default())
+    .with_prompt("Select the target branch")
+    .items(&branches)
+    .default(default)
+    .interact()
+    .map_err(|e| e.to_string())?;
+
+  Ok(branches[index].clone())
+}
+
+/**
+ * 从目标分支根目录下的目录中选择 templates_source
+ */
+async fn select_templates_source(config: &ConfigFile) -> Result<String, String> {
+  let uri = format!(
+    "{}?ref={}",
+    config.get_remote_contents_url(""),
+    encode_query_value(&config.target_branch)
+  );
+  let (status, body) = request_github_api_with_status(&uri, &config.github_api_token, None).await?;
+  if status != 200 {
+    return Err(format!("Request {} failure, code: {}", uri, status));
+  }
+
+  let dirs: Vec<String> = serde_json::from_slice::<RemoteGithubDir>(&body)
+    .map_err(|e| e.to_string())?
+    .into_iter()
+    .filter(|item| item.file_type == "dir" && !item.name.starts_with('.'))
+    .map(|item| item.name)
+    .collect();
+
+  if dirs.is_empty() {
+    println!(
+      "{}",
+      format!(
+        "There is no dir in {} on {}, use {} as templates source.",
+        config.get_repo_full_name(),
+        config.target_branch,
+        DEFAULT_TEMPLATES_SOURCE
+      )
+      .yellow()
+    );
+    return Ok(String::from(DEFAULT_TEMPLATES_SOURCE));
+  }
+
+  let default = dirs
+    .iter()
+    .position(|item| item == &config.templates_source)
+    .or_else(|| {
+      dirs
+        .iter()
+        .position(|item| item == DEFAULT_TEMPLATES_SOURCE)
+    })
+    .unwrap_or(0);
+
+  let index = Select::with_theme(&ColorfulTheme::default())
+    .with_prompt("Select the templates source dir")
+    .items(&dirs)
+    .default(default)
+    .interact()
+    .map_err(|e| e.to_string())?;
+
+  Ok(dirs[index].clone())
+}
+
+/**
+ * 确认目标分支上存在可以解析的 wego.yaml
+ */
+async fn check_remote_yaml(config: &ConfigFile) -> Result<bool, String> {
+  let uri = format!(
+    "{}?ref={}",
+    config.get_remote_yaml_url(),
+    encode_query_value(&config.target_branch)
+  );
+  let (status, body) = request_github_api_with_status(&uri, &config.github_api_token, None).await?;
+
+  if status != 200 {
+    println!(
+      "{} {}",
+      "Warning:".yellow().bold(),
+      format!(
+        "There is no wego.yaml in {} on {}!",
+        config.get_repo_full_name(),
+        config.target_branch
+      )
+      .yellow()
+    );
+    return Ok(false);
+  }
+
+  let content = serde_json::from_slice::<ContentsResponse>(&body).map_err(|e| e.to_string())?;
+  let content_string = content
+    .decode_base64_to_string()
+    .map_err(|e| e.to_string())?;
+
+  match parse_remote_yaml(&content_string) {
+    Ok(manifest) => {
+      let count: usize = manifest
+        .get_types()
+        .iter()
+        .map(|item| item.templates.len())
+        .sum();
+      println!(
+        "{} {}",
+        "Found wego.yaml with".green(),
+        format!("{} templates", count).green()
+      );
+      Ok(true)
+    }
+    Err(e) => {
+      println!(
+        "{} {}",
+        "Warning:".yellow().bold(),
+        format!("wego.yaml exists but can not be parsed:\n{}", e).yellow()
+      );
+      Ok(false)
+    }
+  }
+}
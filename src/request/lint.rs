@@ -0,0 +1,547 @@
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs,
+  path::Path,
+};
+
+use colored::Colorize;
+
+use super::{
+  request::{fetch_remote_yaml_content, fetch_template_tree_shas, get_config},
+  resolve::{format_template_ref, parse_dependency},
+  schema::{find_unknown_fields, parse_remote_yaml, MANIFEST_FILE_NAME},
+  version::{parse_template_spec, parse_version, parse_version_req},
+  RemoteYaml,
+};
+
+pub const LINT_ERROR: &str = "error";
+pub const LINT_WARNING: &str = "warning";
+
+pub const RULE_INVALID_MANIFEST: &str = "invalid-manifest";
+pub const RULE_UNKNOWN_DEPENDENCY: &str = "unknown-dependency";
+pub const RULE_DEPENDENCY_CYCLE: &str = "dependency-cycle";
+pub const RULE_DUPLICATE_NAME: &str = "duplicate-name";
+pub const RULE_MISSING_FOLDER: &str = "missing-folder";
+pub const RULE_UNLISTED_FOLDER: &str = "unlisted-folder";
+pub const RULE_INVALID_VERSION: &str = "invalid-version";
+pub const RULE_UNKNOWN_FIELD: &str = "unknown-field";
+
+/**
+ * 检查 wego.yaml 的参数
+ */
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct LintOptions {
+  /**
+   * 模板仓库的本地目录，用于在模板仓库的 CI 中检查，不传时检查配置的远程仓库
+   */
+  pub path: Option<String>,
+  /**
+   * 本地检查时模板所在的目录，默认 templates
+   */
+  pub templates_source: Option<String>,
+  /**
+   * 为 true 时有错误或无法检查时以退出码 1 退出进程，供模板仓库的 CI 使用
+   * 默认只返回检查出的问题，由调用方决定如何处理
+   */
+  pub exit_on_error: Option<bool>,
+}
+
+/**
+ * wego.yaml 检查出的问题
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+  /**
+   * invalid-manifest | unknown-dependency | dependency-cycle | duplicate-name | missing-folder | unlisted-folder | invalid-version | unknown-field
+   */
+  pub rule: String,
+  /**
+   * error | warning
+   */
+  pub level: String,
+  pub template_type: String,
+  pub name: String,
+  pub message: String,
+}
+
+impl LintIssue {
+  fn new(rule: &str, level: &str, template_type: &str, name: &str, message: String) -> Self {
+    LintIssue {
+      rule: rule.to_string(),
+      level: level.to_string(),
+      template_type: template_type.to_string(),
+      name: name.to_string(),
+      message,
+    }
+  }
+
+  fn is_error(&self) -> bool {
+    self.level == LINT_ERROR
+  }
+}
+
+pub async fn lint_manifest(options: &LintOptions) -> Result<Vec<LintIssue>, String> {
+  let issues = match &options.path {
+    Some(path) => lint_local_manifest(path, options.templates_source.as_deref()),
+    None => lint_remote_manifest().await?,
+  };
+
+  print_lint_issues(&issues);
+
+  Ok(issues)
+}
+
+/**
+ * 检查模板仓库本地目录中的 wego.yaml 和模板目录
+ */
+fn lint_local_manifest(path: &str, templates_source: Option<&str>) -> Vec<LintIssue> {
+  let root = Path::new(path);
+  let manifest_path = root.join(MANIFEST_FILE_NAME);
+  let content = match fs::read_to_string(&manifest_path) {
+    Ok(content) => content,
+    Err(e) => {
+      return vec![LintIssue::new(
+        RULE_INVALID_MANIFEST,
+        LINT_ERROR,
+        "",
+        "",
+        format!("{} can not be read: {}", manifest_path.display(), e),
+      )]
+    }
+  };
+
+  let manifest = match parse_remote_yaml(&content) {
+    Ok(manifest) => manifest,
+    Err(e) => return vec![LintIssue::new(RULE_INVALID_MANIFEST, LINT_ERROR, "", "", e)],
+  };
+
+  let templates_root = root.join(templates_source.unwrap_or("templates"));
+  let mut folders = BTreeMap::new();
+  for template_type in manifest.get_types() {
+    let source = template_type.get_source();
+    let dirs = fs::read_dir(templates_root.join(&source))
+      .map(|entries| {
+        entries
+          .flatten()
+          .filter(|entry| entry.path().is_dir())
+          .map(|entry| entry.file_name().to_string_lossy().to_string())
+          .collect()
+      })
+      .unwrap_or_default();
+    folders.insert(source, dirs);
+  }
+
+  let mut issues = lint_unknown_fields(&content);
+  issues.extend(lint_manifest_with_folders(&manifest, &folders));
+
+  issues
+}
+
+/**
+ * 检查配置的远程仓库中目标分支上的 wego.yaml 和模板目录
+ */
+async fn lint_remote_manifest() -> Result<Vec<LintIssue>, String> {
+  let config = get_config();
+  let (manifest, content) =
+    match fetch_remote_yaml_content(&config.github_api_token, &config.target_branch)
+      .await
+      .and_then(|content| Ok((parse_remote_yaml(&content)?, content)))
+    {
+      Ok(result) => result,
+      Err(e) => {
+        return Ok(vec![LintIssue::new(
+          RULE_INVALID_MANIFEST,
+          LINT_ERROR,
+          "",
+          "",
+          e,
+        )])
+      }
+    };
+
+  // 读取失败的模板目录只报告一次错误，不再按目录检查其中的模板
+  let mut issues = lint_unknown_fields(&content);
+  let mut folders = BTreeMap::new();
+  for template_type in manifest.get_types() {
+    let source = template_type.get_source();
+    match fetch_template_tree_shas(&source, &config.github_api_token, &config.target_branch).await {
+      Ok(shas) => {
+        folders.insert(source, shas.into_keys().collect());
+      }
+      Err(e) => issues.push(LintIssue::new(
+        RULE_INVALID_MANIFEST,
+        LINT_ERROR,
+        &template_type.name,
+        "",
+        format!("templates folder {} can not be read: {}", source, e),
+      )),
+    }
+  }
+
+  issues.extend(lint_manifest_with_folders(&manifest, &folders));
+
+  Ok(issues)
+}
+
+/**
+ * 未声明的字段在解析时会被忽略，只给出警告
+ */
+fn lint_unknown_fields(content: &str) -> Vec<LintIssue> {
+  find_unknown_fields(content)
+    .into_iter()
+    .map(|path| {
+      LintIssue::new(
+        RULE_UNKNOWN_FIELD,
+        LINT_WARNING,
+        "",
+        "",
+        format!("unknown field {} is ignored", path),
+      )
+    })
+    .collect()
+}
+
+/**
+ * folders 为每个模板类型 source 目录下实际存在的模板目录，没有读取到的 source 不检查目录
+ */
+fn lint_manifest_with_folders(
+  manifest: &RemoteYaml,
+  folders: &BTreeMap<String, BTreeSet<String>>,
+) -> Vec<LintIssue> {
+  let mut issues = vec![];
+
+  for template_type in manifest.get_types() {
+    let mut names = BTreeSet::new();
+
+    for template in &template_type.templates {
+      if !names.insert(template.name.clone()) {
+        issues.push(LintIssue::new(
+          RULE_DUPLICATE_NAME,
+          LINT_ERROR,
+          &template_type.name,
+          &template.name,
+          format!(
+            "{} is declared more than once in {}",
+            template.name, template_type.name
+          ),
+        ));
+      }
+
+      if let Some(Err(e)) = template.version.as_deref().map(parse_version) {
+        issues.push(LintIssue::new(
+          RULE_INVALID_VERSION,
+          LINT_ERROR,
+          &template_type.name,
+          &template.name,
+          e,
+        ));
+      }
+
+      // 可选依赖和功能组中的依赖同样需要声明
+      let deps = template
+        .dependencies
+        .iter()
+        .flatten()
+        .chain(template.optional_dependencies.iter().flatten())
+        .chain(
+          template
+            .features
+            .iter()
+            .flatten()
+            .flat_map(|(_, deps)| deps),
+        );
+      for dep in deps {
+        let spec = parse_template_spec(dep);
+        let version_error = match (spec.version, spec.git_ref) {
+          (Some(_), Some(_)) => Some(String::from(
+            "a version constraint and a ref can not be used together",
+          )),
+          (Some(version), None) => parse_version_req(version).err(),
+          _ => None,
+        };
+        if let Some(e) = version_error {
+          issues.push(LintIssue::new(
+            RULE_INVALID_VERSION,
+            LINT_ERROR,
+            &template_type.name,
+            &template.name,
+            format!("{} depends on {}, {}", template.name, dep, e),
+          ));
+        }
+
+        let (dep_type, dep_name) = parse_dependency(manifest, dep);
+        if !manifest
+          .get_templates(&dep_type)
+          .iter()
+          .any(|item| item.name == dep_name)
+        {
+          issues.push(LintIssue::new(
+            RULE_UNKNOWN_DEPENDENCY,
+            LINT_ERROR,
+            &template_type.name,
+            &template.name,
+            format!(
+              "{} depends on {}, which is not declared in {}",
+              template.name, dep, dep_type
+            ),
+          ));
+        }
+      }
+    }
+
+    let source = template_type.get_source();
+    let dirs = match folders.get(&source) {
+      Some(dirs) => dirs,
+      None => continue,
+    };
+
+    for name in &names {
+      if !dirs.contains(name) {
+        issues.push(LintIssue::new(
+          RULE_MISSING_FOLDER,
+          LINT_ERROR,
+          &template_type.name,
+          name,
+          format!("there is no folder {}/{}", source, name),
+        ));
+      }
+    }
+    for dir in dirs.difference(&names) {
+      issues.push(LintIssue::new(
+        RULE_UNLISTED_FOLDER,
+        LINT_WARNING,
+        &template_type.name,
+        dir,
+        format!(
+          "folder {}/{} is not declared in {}",
+          source, dir, MANIFEST_FILE_NAME
+        ),
+      ));
+    }
+  }
+
+  for cycle in find_dependency_cycles(manifest) {
+    let (type_name, name) = parse_dependency(manifest, &cycle[0]);
+    issues.push(LintIssue::new(
+      RULE_DEPENDENCY_CYCLE,
+      LINT_ERROR,
+      &type_name,
+      &name,
+      format!("dependency cycle: {}", cycle.join(" -> ")),
+    ));
+  }
+
+  issues
+}
+
+/**
+ * 找出所有类型模板依赖图中的环，节点为 button、pages/login 形式的引用
+ * 每个环以最小的引用开头且首尾相同，例如 [a, b, a]
+ */
+fn find_dependency_cycles(manifest: &RemoteYaml) -> Vec<Vec<String>> {
+  // 重复声明的模板合并依赖，重复本身由 duplicate-name 报告
+  // 可选依赖和功能组被选择后同样会被解析，也作为依赖图的边
+  let mut graph: BTreeMap<String, Vec<String>> = BTreeMap::new();
+  for template_type in manifest.get_types() {
+    for item in template_type.templates {
+      let deps = item
+        .dependencies
+        .iter()
+        .flatten()
+        .chain(item.optional_dependencies.iter().flatten())
+        .chain(item.features.iter().flatten().flat_map(|(_, deps)| deps))
+        .map(|dep| {
+          let (dep_type, dep_name) = parse_dependency(manifest, dep);
+          format_template_ref(&dep_type, &dep_name)
+        })
+        .collect::<Vec<String>>();
+      graph
+        .entry(format_template_ref(&template_type.name, &item.name))
+        .or_default()
+        .extend(deps);
+    }
+  }
+
+  let mut cycles = BTreeSet::new();
+  let mut visited = BTreeSet::new();
+  for name in graph.keys() {
+    let mut stack = vec![];
+    visit_dependency(name, &graph, &mut stack, &mut visited, &mut cycles);
+  }
+
+  cycles.into_iter().collect()
+}
+
+fn visit_dependency(
+  name: &str,
+  graph: &BTreeMap<String, Vec<String>>,
+  stack: &mut Vec<String>,
+  visited: &mut BTreeSet<String>,
+  cycles: &mut BTreeSet<Vec<String>>,
+) {
+  if let Some(index) = stack.iter().position(|item| item == name) {
+    cycles.insert(normalize_cycle(&stack[index..]));
+    return;
+  }
+  if visited.contains(name) {
+    return;
+  }
+
+  stack.push(name.to_string());
+  for dep in graph.get(name).into_iter().flatten() {
+    visit_dependency(dep, graph, stack, visited, cycles);
+  }
+  stack.pop();
+  visited.insert(name.to_string());
+}
+
+/**
+ * 把环旋转到以最小的名称开头，避免同一个环从不同入口被重复报告
+ */
+fn normalize_cycle(cycle: &[String]) -> Vec<String> {
+  let start = cycle
+    .iter()
+    .enumerate()
+    .min_by(|a, b| a.1.cmp(b.1))
+    .map(|(index, _)| index)
+    .unwrap_or(0);
+
+  let mut result: Vec<String> = cycle[start..]
+    .iter()
+    .chain(cycle[..start].iter())
+    .cloned()
+    .collect();
+  result.push(result[0].clone());
+
+  result
+}
+
+fn print_lint_issues(issues: &[LintIssue]) {
+  for issue in issues {
+    let level = match issue.level.as_str() {
+      LINT_ERROR => format!("[{}]", issue.level).red(),
+      _ => format!("[{}]", issue.level).yellow(),
+    };
+    let target = if issue.name.is_empty() {
+      String::from(MANIFEST_FILE_NAME)
+    } else {
+      format!("{}/{}", issue.template_type, issue.name)
+    };
+
+    println!(
+      "{} {} {} ----> {}",
+      level,
+      issue.rule.white(),
+      target.bold(),
+      issue.message
+    );
+  }
+
+  let errors = issues.iter().filter(|issue| issue.is_error()).count();
+  let warnings = issues.len() - errors;
+  if issues.is_empty() {
+    println!("{}", "No problem found!".green());
+  } else {
+    println!(
+      "{}",
+      format!("{} errors, {} warnings", errors, warnings).red()
+    );
+  }
+}
+
+pub fn has_lint_errors(issues: &[LintIssue]) -> bool {
+  issues.iter().any(|issue| issue.is_error())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(content: &str) -> RemoteYaml {
+    parse_remote_yaml(content).unwrap()
+  }
+
+  #[test]
+  fn finds_cycles_through_optional_dependencies_and_features() {
+    let manifest = parse(
+      r#"
+components:
+  - name: a
+    description: a
+    dependencies: [b]
+  - name: b
+    description: b
+    optional_dependencies: [c]
+  - name: c
+    description: c
+    features:
+      extra: [a]
+  - name: d
+    description: d
+    dependencies: [a]
+"#,
+    );
+
+    assert_eq!(
+      find_dependency_cycles(&manifest),
+      vec![vec!["a", "b", "c", "a"]]
+    );
+  }
+
+  #[test]
+  fn reports_each_cycle_once_starting_from_the_smallest_name() {
+    let manifest = parse(
+      r#"
+components:
+  - name: button
+    description: button
+    dependencies: [pages/login]
+pages:
+  - name: login
+    description: login
+    dependencies: [button, pages/login]
+"#,
+    );
+
+    assert_eq!(
+      find_dependency_cycles(&manifest),
+      vec![
+        vec!["button", "pages/login", "button"],
+        vec!["pages/login", "pages/login"],
+      ]
+    );
+  }
+
+  #[test]
+  fn checks_folders_only_for_sources_that_were_read() {
+    let manifest = parse(
+      r#"
+components:
+  - name: button
+    description: button
+pages:
+  - name: login
+    description: login
+"#,
+    );
+    let folders = BTreeMap::from([(
+      String::from("components"),
+      BTreeSet::from([String::from("input")]),
+    )]);
+
+    let rules: Vec<(String, String)> = lint_manifest_with_folders(&manifest, &folders)
+      .into_iter()
+      .map(|issue| (issue.rule, issue.name))
+      .collect();
+
+    assert_eq!(
+      rules,
+      vec![
+        (String::from(RULE_MISSING_FOLDER), String::from("button")),
+        (String::from(RULE_UNLISTED_FOLDER), String::from("input")),
+      ]
+    );
+  }
+}
@@ -1,392 +1,300 @@
-use std::{fs, path::Path, pin::Pin, time::Instant};
+use std::collections::BTreeMap;
 
 use base64::decode;
-use colored::Colorize;
-use dialoguer::{console::Term, theme::ColorfulTheme, Input, Select};
-use futures::Future;
-use hyper::{
-  header::{AUTHORIZATION, USER_AGENT},
-  Body, Request,
-};
+use schemars::JsonSchema;
 use serde::Deserialize;
 
-use crate::{constants::FILE_MARKER, helper::ConfigFile, TemplateType, CLIENT};
+use crate::TemplateType;
 
-pub mod request;
+use self::types::TypeConfig;
 
-#[derive(Debug, Deserialize, Clone)]
+pub mod changelog;
+pub mod diff;
+pub mod doctor;
+pub mod init;
+pub mod lint;
+pub mod outdated;
+pub mod plan;
+pub mod refs;
+pub mod remove;
+#[allow(clippy::module_inception)]
+pub mod request;
+pub mod resolve;
+pub mod schema;
+pub mod search;
+pub mod types;
+pub mod upgrade;
+pub mod version;
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
 pub struct ConfigYaml {
   pub name: String,
   pub description: String,
+  /**
+   * 搜索和筛选用的标签，例如 form、table
+   */
+  pub tags: Option<Vec<String>>,
+  /**
+   * 模板分类，例如 data-display、feedback
+   */
+  pub category: Option<String>,
+  /**
+   * 模板的维护者
+   */
+  pub author: Option<String>,
+  /**
+   * 模板在目标分支上的版本，也可以通过 button@1.4.0 形式的 tag 发布版本
+   */
+  pub version: Option<String>,
+  /**
+   * 依赖的模板，只写名称时为 components，其他类型写作 pages/login、hooks/useAuth
+   * 可以用 button@^1.2 限定依赖的版本，或用 button#main 指定分支、tag 或 commit sha
+   */
   pub dependencies: Option<Vec<String>>,
+  /**
+   * 可选依赖，生成时通过多选询问，只安装选择的依赖
+   */
+  pub optional_dependencies: Option<Vec<String>>,
+  /**
+   * 命名的功能组，例如 export: [utils/csv, button]，选择功能组时安装其中所有依赖
+   */
+  pub features: Option<BTreeMap<String, Vec<String>>>,
+  /**
+   * 模板默认的生成目录，本地 wego.yaml 配置了该类模板的 destinations 时不生效
+   */
+  pub destination: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Projects {
-  name: String,
-  description: String,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct RemoteYaml {
-  pub components: Vec<ConfigYaml>,
-  pub pages: Vec<ConfigYaml>,
-  pub projects: Vec<Projects>,
-}
-
-pub fn get_local_dir_path() -> String {
-  let binding = std::env::current_dir()
-    .unwrap()
-    .to_str()
-    .unwrap()
-    .to_string();
-
-  binding
-}
+impl ConfigYaml {
+  /**
+   * 选择列表中名称和描述之后的分类、标签和作者，例如 [form] #input #antd @alice
+   */
+  pub fn get_labels(&self) -> String {
+    let mut labels: Vec<String> = vec![];
 
-pub fn create_dir_to_local(name: String, temp_type: TemplateType) -> String {
-  let curr_path = get_local_dir_path();
-  let type_url: String;
-  match temp_type {
-    TemplateType::Components => type_url = String::from("src/components"),
-    TemplateType::Pages => type_url = String::from("src/pages"),
-    TemplateType::Project => type_url = String::from(""),
-  }
+    if let Some(category) = &self.category {
+      labels.push(format!("[{}]", category));
+    }
+    for tag in self.tags.iter().flatten() {
+      labels.push(format!("#{}", tag));
+    }
+    if let Some(author) = &self.author {
+      labels.push(format!("@{}", author));
+    }
 
-  let path_vec = vec![curr_path, type_url, name];
-  if let Err(e) = fs::create_dir_all(path_vec.join(FILE_MARKER)) {
-    println!("Create dir failure: {:#?}", e);
+    labels.join(" ")
   }
 
-  let path_vec: Vec<String> = path_vec
-    .iter()
-    .filter(|item| !item.is_empty())
-    .cloned()
-    .collect();
-
-  path_vec.join(FILE_MARKER)
-}
-
-pub fn get_local_dir(name: String, temp_type: TemplateType) -> String {
-  let curr_path = get_local_dir_path();
-  let type_url: String;
-  match temp_type {
-    TemplateType::Components => type_url = String::from("src/components"),
-    TemplateType::Pages => type_url = String::from("src/pages"),
-    TemplateType::Project => type_url = String::from(""),
+  pub fn has_optional_dependencies(&self) -> bool {
+    self
+      .optional_dependencies
+      .as_ref()
+      .map(|deps| !deps.is_empty())
+      .unwrap_or(false)
+      || self
+        .features
+        .as_ref()
+        .map(|features| !features.is_empty())
+        .unwrap_or(false)
   }
-  let path_vec = vec![curr_path, type_url, name];
-
-  path_vec.join(FILE_MARKER)
-}
 
-pub async fn fetch_remote_dir(
-  url: String,
-  token: &str,
-  tb: &str,
-) -> Result<RemoteGithubDir, String> {
-  let uri = format!("{}?{}={}", &url, "ref", &tb);
-  let req_result = Request::builder()
-    .uri(uri)
-    .method("GET")
-    .header(AUTHORIZATION, format!("Bearer {}", token))
-    .header(USER_AGENT, "wego")
-    .body(Body::empty());
-
-  match req_result {
-    Ok(req) => {
-      let fetch_res = CLIENT.request(req).await;
-      match fetch_res {
-        Ok(res) => {
-          if res.status() == 200 {
-            let body_bytes = hyper::body::to_bytes(res.into_body())
-              .await
-              .expect("Parse error!!");
-            let content_res = serde_json::from_slice::<RemoteGithubDir>(&body_bytes);
-
-            match content_res {
-              Ok(content) => {
-                return Ok(content);
-              }
-              Err(e) => {
-                println!("{:?}", e.to_string().red());
-              }
-            }
-          } else {
-            println!(
-              "{} {}",
-              "Request remote dir failure, code: ".red(),
-              res.status().as_str().red().bold()
-            )
-          }
+  /**
+   * 把选择的功能组和可选依赖展开为依赖列表，未声明的选项会被忽略
+   */
+  pub fn get_selected_dependencies(&self, selected: &[String]) -> Vec<String> {
+    let mut deps: Vec<String> = vec![];
+
+    for item in selected {
+      let expanded = match self
+        .features
+        .as_ref()
+        .and_then(|features| features.get(item))
+      {
+        Some(feature_deps) => feature_deps.clone(),
+        None
+          if self
+            .optional_dependencies
+            .iter()
+            .flatten()
+            .any(|dep| dep == item) =>
+        {
+          vec![item.clone()]
         }
-        Err(e) => {
-          println!("{:?}", e.to_string().red());
+        None => vec![],
+      };
+
+      for dep in expanded {
+        if !deps.contains(&dep) {
+          deps.push(dep);
         }
       }
     }
-    Err(e) => {
-      println!("{:?}", e.to_string().red());
-    }
+
+    deps
   }
-  return Err(String::from(""));
-}
 
-pub fn find_all_deps(deps: Vec<String>, list: Vec<ConfigYaml>) -> Vec<String> {
-  let mut this_deps = deps.clone();
-  for item in &list {
-    if this_deps.contains(&item.name) {
-      if let Some(deps) = &item.dependencies {
-        if &deps.len() > &0 {
-          if let Some(dep) = &item.dependencies {
-            for dep_item in dep {
-              if !this_deps.contains(dep_item) {
-                this_deps.push(dep_item.to_string());
-                this_deps = find_all_deps(this_deps, list.clone());
-              }
-            }
-          }
-        }
-      }
-    }
+  /**
+   * 选择中既不是功能组也不是可选依赖的名称
+   */
+  pub fn get_unknown_selections(&self, selected: &[String]) -> Vec<String> {
+    selected
+      .iter()
+      .filter(|item| {
+        !self
+          .features
+          .as_ref()
+          .map(|features| features.contains_key(*item))
+          .unwrap_or(false)
+          && !self
+            .optional_dependencies
+            .iter()
+            .flatten()
+            .any(|dep| dep == *item)
+      })
+      .map(|item| {
+        format!(
+          "{} is not an optional dependency or feature of {}",
+          item, self.name
+        )
+      })
+      .collect()
   }
+}
 
-  this_deps
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct Projects {
+  name: String,
+  description: String,
+  tags: Option<Vec<String>>,
+  category: Option<String>,
+  author: Option<String>,
+  version: Option<String>,
+  destination: Option<String>,
+  /**
+   * 项目创建后安装到项目中的组件、页面等模板，写法与 ConfigYaml 的 dependencies 相同
+   */
+  dependencies: Option<Vec<String>>,
 }
 
-impl RemoteYaml {
-  pub fn get_output_string_vec(&self, output_type: TemplateType) -> Vec<String> {
-    let mut result: Vec<String> = vec![];
-    match output_type {
-      TemplateType::Components => {
-        for component in &self.components {
-          result.push(format!(
-            "{} ----> {} ----> {:?}",
-            component.name, component.description, component.dependencies
-          ));
-        }
-        result
-      }
-      TemplateType::Pages => {
-        for component in &self.pages {
-          result.push(format!(
-            "{} ----> {} ----> {:?}",
-            component.name, component.description, component.dependencies
-          ));
-        }
-        result
-      }
-      TemplateType::Project => {
-        for component in &self.projects {
-          result.push(format!(
-            "{} ----> {}",
-            component.name, component.description
-          ));
-        }
-        result
-      }
+impl From<&Projects> for ConfigYaml {
+  fn from(project: &Projects) -> Self {
+    ConfigYaml {
+      name: project.name.clone(),
+      description: project.description.clone(),
+      tags: project.tags.clone(),
+      category: project.category.clone(),
+      author: project.author.clone(),
+      version: project.version.clone(),
+      dependencies: project.dependencies.clone(),
+      optional_dependencies: None,
+      features: None,
+      destination: project.destination.clone(),
     }
   }
+}
 
-  pub async fn show_projects_templates<F>(
-    &self,
-    run_job: F,
-    config_file: &ConfigFile,
-  ) -> Result<(), Box<dyn std::error::Error>>
-  where
-    F: Fn(Vec<GithubRequestDir>, String, String, String) -> Pin<Box<dyn Future<Output = ()>>>,
-  {
-    let projects = &self.projects;
-
-    let items = self.get_output_string_vec(TemplateType::Project);
-
-    let selection = Select::with_theme(&ColorfulTheme::default())
-      .items(&items)
-      .default(0)
-      .interact_on_opt(&Term::stderr())?;
-
-    if let Some(index) = selection {
-      let this_project = &projects[index];
-      let project_name = &this_project.name;
-
-      let custom_name: String = Input::new()
-        .with_prompt("Custom file name(Not required)")
-        .allow_empty(true)
-        .interact_text()?;
-
-      let final_file_name: String;
-
-      if custom_name == "" {
-        final_file_name = project_name.clone().to_string();
-      } else {
-        final_file_name = custom_name;
-      }
-      let token: String = String::from(&config_file.github_api_token);
-
-      let request_url = config_file.get_remote_template_url(TemplateType::Project, project_name);
-
-      let res = fetch_remote_dir(request_url.clone(), &token, &config_file.target_branch).await;
-      match res {
-        Ok(info) => {
-          let local_path = create_dir_to_local(final_file_name, TemplateType::Project);
-
-          let start_time = Instant::now();
-
-          run_job(info, local_path, token, request_url.clone()).await;
+/**
+ * 远程仓库中的 wego.yaml
+ */
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoteYaml {
+  #[serde(default)]
+  pub components: Vec<ConfigYaml>,
+  #[serde(default)]
+  pub pages: Vec<ConfigYaml>,
+  #[serde(default)]
+  pub projects: Vec<Projects>,
+  /**
+   * 自定义模板类型，也可以用来修改内置类型的 source、destination 和 label
+   */
+  #[serde(default)]
+  pub types: Vec<TypeConfig>,
+}
 
-          println!("Done in {:?} ms!", start_time.elapsed().as_millis());
-        }
-        Err(e) => {
-          println!("Error: {:}", e);
+impl RemoteYaml {
+  /**
+   * 内置类型与 types 中声明的所有模板类型
+   */
+  pub fn get_types(&self) -> Vec<TypeConfig> {
+    let mut types = vec![
+      TypeConfig::new(TemplateType::Components.dir_name(), self.components.clone()),
+      TypeConfig::new(TemplateType::Pages.dir_name(), self.pages.clone()),
+      TypeConfig::new(
+        TemplateType::Project.dir_name(),
+        self.projects.iter().map(ConfigYaml::from).collect(),
+      ),
+    ];
+
+    for item in &self.types {
+      match types.iter_mut().find(|t_type| t_type.name == item.name) {
+        Some(builtin) => {
+          builtin.source = item.source.clone();
+          builtin.destination = item.destination.clone();
+          builtin.label = item.label.clone();
+          builtin.templates.extend(item.templates.clone());
         }
+        None => types.push(item.clone()),
       }
     }
 
-    Ok(())
+    types
   }
 
-  pub fn get_page_templates_items_by_type(&self, t_type: TemplateType) -> Vec<String> {
-    self.get_output_string_vec(t_type)
+  pub fn get_type(&self, type_name: &str) -> Option<TypeConfig> {
+    self
+      .get_types()
+      .into_iter()
+      .find(|item| item.name == type_name)
   }
 
-  pub async fn show_pages_templates<F>(
-    &self,
-    run_job: F,
-    config_file: &ConfigFile,
-  ) -> Result<(), Box<dyn std::error::Error>>
-  where
-    F: Fn(Vec<GithubRequestDir>, String, String, String) -> Pin<Box<dyn Future<Output = ()>>>,
-  {
-    let pages = &self.pages;
-
-    let items = self.get_output_string_vec(TemplateType::Pages);
-
-    let selection = Select::with_theme(&ColorfulTheme::default())
-      .items(&items)
-      .default(0)
-      .interact_on_opt(&Term::stderr())?;
-
-    if let Some(index) = selection {
-      let this_page = &pages[index];
-      let file_name = &this_page.name;
-      let deps = &this_page.dependencies;
-
-      let custom_name: String = Input::new()
-        .with_prompt("Custom file name(Not required)")
-        .allow_empty(true)
-        .interact_text()?;
-
-      let final_file_name: String;
-
-      if custom_name == "" {
-        final_file_name = file_name.clone().to_string();
-      } else {
-        final_file_name = custom_name;
-      }
+  pub fn get_templates(&self, type_name: &str) -> Vec<ConfigYaml> {
+    self
+      .get_type(type_name)
+      .map(|item| item.templates)
+      .unwrap_or_default()
+  }
 
-      let token: String = String::from(&config_file.github_api_token);
-
-      let request_url = config_file.get_remote_template_url(TemplateType::Pages, file_name);
-      let res = fetch_remote_dir(request_url.clone(), &token, &config_file.target_branch).await;
-      if let Ok(info) = res {
-        let local_path = create_dir_to_local(final_file_name, TemplateType::Pages);
-
-        let start_time = Instant::now();
-        let _ = &run_job(info, local_path, token.to_string(), request_url.clone()).await;
-        println!("Done in {:?} ms!", start_time.elapsed().as_millis());
-
-        if let Some(dep) = deps {
-          if &dep.len() > &0 {
-            println!("{} {:?}", "Start loading dependencies ---->".green(), &dep);
-            let all = find_all_deps(dep.clone(), self.components.clone());
-            self
-              .download_components_to_local(all, token.clone(), &run_job, config_file)
-              .await;
-          }
-        }
-      } else {
-        println!(
-          "{} {}",
-          "Error: request templates failure! ".red(),
-          &request_url
-        );
-      }
-    }
+  /**
+   * 获取模板直接声明的必需依赖，不包含可选依赖
+   */
+  pub fn get_dependencies(&self, type_name: &str, name: &str) -> Vec<String> {
+    self
+      .get_templates(type_name)
+      .into_iter()
+      .find(|item| item.name == name)
+      .and_then(|item| item.dependencies)
+      .unwrap_or_default()
+  }
 
-    Ok(())
+  /**
+   * 模板类型在 templates_source 下的目录
+   */
+  pub fn get_source(&self, type_name: &str) -> String {
+    self
+      .get_type(type_name)
+      .map(|item| item.get_source())
+      .unwrap_or_else(|| type_name.to_string())
   }
 
-  pub async fn show_components_templates<F>(
-    &self,
-    run_job: F,
-    config_file: &ConfigFile,
-  ) -> Result<(), Box<dyn std::error::Error>>
-  where
-    F: Fn(Vec<GithubRequestDir>, String, String, String) -> Pin<Box<dyn Future<Output = ()>>>,
-  {
-    let components = &self.components;
-
-    let items = self.get_output_string_vec(TemplateType::Components);
-
-    let selection = Select::with_theme(&ColorfulTheme::default())
-      .items(&items)
-      .default(0)
-      .interact_on_opt(&Term::stderr())?;
-
-    if let Some(index) = selection {
-      let this_comp = &components[index];
-      let file_name = &this_comp.name;
-
-      if let Some(ref mut deps) = this_comp.dependencies.clone() {
-        deps.push(String::from(file_name));
-
-        let all = find_all_deps(deps.to_vec(), components.to_vec());
-        let token: String = String::from(&config_file.github_api_token);
-        self
-          .download_components_to_local(all, token, &run_job, config_file)
-          .await;
-      }
-    }
-    Ok(())
+  /**
+   * 模板在远程 wego.yaml 中声明的版本
+   */
+  pub fn get_version(&self, type_name: &str, name: &str) -> Option<String> {
+    self
+      .get_templates(type_name)
+      .into_iter()
+      .find(|item| item.name == name)
+      .and_then(|item| item.version)
   }
 
-  pub async fn download_components_to_local<F>(
-    &self,
-    coms: Vec<String>,
-    token: String,
-    run_job: F,
-    config_file: &ConfigFile,
-  ) where
-    F: Fn(Vec<GithubRequestDir>, String, String, String) -> Pin<Box<dyn Future<Output = ()>>>,
-  {
-    if coms.len() > 0 {
-      let start_time = Instant::now();
-      let mut async_tasks = vec![];
-      for item in coms {
-        let local_path = get_local_dir(item.clone(), TemplateType::Components);
-
-        let p = Path::new(&local_path);
-
-        if p.exists() {
-          println!(
-            "{} {} is already existed!",
-            "Warning:".red().bold(),
-            local_path.red()
-          );
-        } else {
-          let request_url = config_file.get_remote_template_url(TemplateType::Components, &item);
-          let res = fetch_remote_dir(request_url.clone(), &token, &config_file.target_branch).await;
-
-          if let Ok(info) = res {
-            let local_path = create_dir_to_local(item.clone(), TemplateType::Components);
-            let handle = run_job(info, local_path, token.to_string(), request_url.clone());
-            async_tasks.push(handle);
-          }
-        }
-      }
-      futures::future::join_all(async_tasks).await;
-      println!("Done in {:?} ms!", start_time.elapsed().as_millis());
-    }
+  /**
+   * 模板在远程 wego.yaml 中声明的生成目录
+   */
+  pub fn get_destination(&self, type_name: &str, name: &str) -> Option<String> {
+    let t_type = self.get_type(type_name)?;
+    let template = t_type.templates.iter().find(|item| item.name == name)?;
+
+    t_type.get_destination(template)
   }
 }
 
@@ -420,7 +328,7 @@ impl ContentsResponse {
       .content
       .as_bytes()
       .iter()
-      .filter(|b| !b" \n\t\r\x0b\x0c".contains(&b))
+      .filter(|b| !b" \n\t\r\x0b\x0c".contains(b))
       .copied()
       .collect::<Vec<u8>>();
     let standard_base64_string = String::from_utf8(r)?;
@@ -434,7 +342,7 @@ impl ContentsResponse {
       .content
       .as_bytes()
       .iter()
-      .filter(|b| !b" \n\t\r\x0b\x0c".contains(&b))
+      .filter(|b| !b" \n\t\r\x0b\x0c".contains(b))
       .copied()
       .collect::<Vec<u8>>();
     let standard_base64_string = String::from_utf8(r)?;
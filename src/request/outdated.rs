@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use colored::Colorize;
+
+use crate::helper::lock::{LockFile, LockedTemplate};
+
+use super::{
+  request::{fetch_remote_yaml, fetch_template_tree_shas, get_config, TemplateRevision},
+  version::resolve_locked_revision,
+};
+
+pub const STATUS_UNCHANGED: &str = "unchanged";
+pub const STATUS_UPDATED: &str = "updated";
+pub const STATUS_REMOVED: &str = "removed";
+pub const STATUS_MODIFIED: &str = "modified";
+pub const STATUS_ERROR: &str = "error";
+
+/**
+ * 已安装模板与远程模板的对比结果
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct OutdatedTemplate {
+  pub name: String,
+  pub template_type: String,
+  pub path: String,
+  /**
+   * unchanged | updated | removed | modified | error
+   */
+  pub status: String,
+  pub locally_modified: bool,
+  pub installed_commit: String,
+  pub installed_tree: String,
+  pub latest_tree: Option<String>,
+  /**
+   * 安装时有版本约束的模板记录的版本和约束范围内的最新版本
+   */
+  pub installed_version: Option<String>,
+  pub latest_version: Option<String>,
+  /**
+   * 获取远程版本失败的原因，此时 status 为 error
+   */
+  pub error: Option<String>,
+}
+
+pub async fn check_outdated_templates() -> Vec<OutdatedTemplate> {
+  let lock_file = match LockFile::read_from_local() {
+    Ok(lock_file) => lock_file,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      return vec![];
+    }
+  };
+
+  if lock_file.templates.is_empty() {
+    println!(
+      "{}",
+      "There is no installed template in wego.lock!".yellow()
+    );
+    return vec![];
+  }
+
+  let config = get_config();
+  let repo = config.get_repo_full_name();
+  let head = TemplateRevision {
+    git_ref: config.target_branch.clone(),
+    ..Default::default()
+  };
+  // 有版本约束的模板与约束范围内的最新版本对比，而不是 head
+  let manifest = if lock_file
+    .templates
+    .iter()
+    .any(|item| !item.version_req.is_empty())
+  {
+    fetch_remote_yaml(&config.github_api_token, &config.target_branch)
+      .await
+      .ok()
+  } else {
+    None
+  };
+  // 请求失败的结果也缓存起来，同一目录的其他模板不再重复请求
+  let mut latest_trees: BTreeMap<(String, String), Result<BTreeMap<String, String>, String>> =
+    BTreeMap::new();
+  let mut result = vec![];
+
+  for template in &lock_file.templates {
+    if template.repo != repo {
+      println!(
+        "{} {} is installed from {}, skipped.",
+        "Warning:".red().bold(),
+        template.path.red(),
+        template.repo
+      );
+      continue;
+    }
+
+    let source = template.get_source();
+    let head_version = manifest
+      .as_ref()
+      .and_then(|manifest| manifest.get_version(&template.template_type, &template.name));
+    let revision = match resolve_locked_revision(
+      &config.github_api_token,
+      template,
+      &head,
+      head_version.as_deref(),
+    )
+    .await
+    {
+      Ok(revision) => revision,
+      Err(e) => {
+        result.push(failed_template(template, e));
+        continue;
+      }
+    };
+    let key = (source.to_string(), revision.fetch_ref().to_string());
+
+    if !latest_trees.contains_key(&key) {
+      let trees =
+        fetch_template_tree_shas(source, &config.github_api_token, revision.fetch_ref()).await;
+      latest_trees.insert(key.clone(), trees);
+    }
+
+    let latest_tree = match &latest_trees[&key] {
+      Ok(trees) => trees.get(&template.name).cloned(),
+      Err(e) => {
+        result.push(failed_template(template, e.clone()));
+        continue;
+      }
+    };
+
+    let mut item = compare_template(template, latest_tree);
+    item.latest_version = Some(revision.version).filter(|version| !version.is_empty());
+    result.push(item);
+  }
+
+  print_outdated_templates(&result);
+
+  result
+}
+
+fn compare_template(template: &LockedTemplate, latest_tree: Option<String>) -> OutdatedTemplate {
+  let locally_modified = template.is_locally_modified();
+
+  let status = match &latest_tree {
+    None => STATUS_REMOVED,
+    Some(tree) if tree != &template.tree => STATUS_UPDATED,
+    Some(_) if locally_modified => STATUS_MODIFIED,
+    Some(_) => STATUS_UNCHANGED,
+  };
+
+  OutdatedTemplate {
+    name: template.name.clone(),
+    template_type: template.template_type.clone(),
+    path: template.path.clone(),
+    status: status.to_string(),
+    locally_modified,
+    installed_commit: template.commit.clone(),
+    installed_tree: template.tree.clone(),
+    installed_version: Some(template.version.clone()).filter(|version| !version.is_empty()),
+    latest_tree,
+    latest_version: None,
+    error: None,
+  }
+}
+
+/**
+ * 获取远程版本失败的模板，记录失败原因后继续检查其他模板
+ */
+fn failed_template(template: &LockedTemplate, error: String) -> OutdatedTemplate {
+  OutdatedTemplate {
+    status: STATUS_ERROR.to_string(),
+    latest_tree: None,
+    error: Some(error),
+    ..compare_template(template, None)
+  }
+}
+
+fn print_outdated_templates(list: &[OutdatedTemplate]) {
+  for item in list {
+    let status = match item.status.as_str() {
+      STATUS_UNCHANGED => item.status.green(),
+      STATUS_UPDATED => "updated upstream".yellow().bold(),
+      STATUS_REMOVED => "removed upstream".red().bold(),
+      STATUS_ERROR => format!("check failure: {}", item.error.clone().unwrap_or_default())
+        .red()
+        .bold(),
+      _ => "locally modified".cyan(),
+    };
+    let modified = if item.locally_modified && item.status != STATUS_MODIFIED {
+      format!(" {}", "(locally modified)".cyan())
+    } else {
+      String::new()
+    };
+    let version = match (&item.installed_version, &item.latest_version) {
+      (Some(installed), Some(latest)) if installed != latest => {
+        format!(" ({} -> {})", installed, latest)
+      }
+      (Some(installed), _) => format!(" ({})", installed),
+      _ => String::new(),
+    };
+
+    println!(
+      "{} {} ----> {}{}{}",
+      format!("[{}]", item.template_type).white(),
+      item.path,
+      status,
+      version.white(),
+      modified
+    );
+  }
+}
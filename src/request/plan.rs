@@ -0,0 +1,199 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+
+use crate::{
+  helper::{conflict::get_available_path, lock::to_relative_path},
+  ConflictStrategy, PlanFormat,
+};
+
+use super::request::get_local_dir_path;
+
+pub const ACTION_CREATE: &str = "create";
+pub const ACTION_OVERWRITE: &str = "overwrite";
+pub const ACTION_UNCHANGED: &str = "unchanged";
+pub const ACTION_SKIP: &str = "skip";
+pub const ACTION_PROMPT: &str = "prompt";
+pub const ACTION_MERGE: &str = "merge";
+
+/**
+ * dry run 时输出的生成计划
+ */
+#[napi(object)]
+#[derive(Debug, Default, Serialize)]
+pub struct GenerationPlan {
+  pub templates: Vec<PlannedTemplate>,
+}
+
+#[napi(object)]
+#[derive(Debug, Serialize)]
+pub struct PlannedTemplate {
+  pub name: String,
+  #[serde(rename = "type")]
+  pub template_type: String,
+  pub destination: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub backup: Option<String>,
+  pub action: String,
+  pub dependency: bool,
+  pub files: Vec<PlannedFile>,
+}
+
+#[napi(object)]
+#[derive(Debug, Serialize)]
+pub struct PlannedFile {
+  pub path: String,
+  pub action: String,
+}
+
+/**
+ * 按冲突策略推算模板写入本地时的操作，只读取本地文件，不做任何修改
+ */
+pub fn plan_template_files(
+  name: &str,
+  type_name: &str,
+  dest: &str,
+  files: &BTreeMap<String, Vec<u8>>,
+  strategy: ConflictStrategy,
+) -> PlannedTemplate {
+  let exists = Path::new(dest).exists();
+  let mut destination = dest.to_string();
+  let mut backup = None;
+
+  let action = if !exists {
+    ACTION_CREATE
+  } else {
+    match strategy {
+      ConflictStrategy::Skip => ACTION_SKIP,
+      ConflictStrategy::Overwrite => ACTION_OVERWRITE,
+      ConflictStrategy::Backup => {
+        backup = Some(get_available_path(&format!("{}.bak", dest)));
+        ACTION_OVERWRITE
+      }
+      ConflictStrategy::Rename => {
+        destination = get_available_path(dest);
+        ACTION_CREATE
+      }
+      ConflictStrategy::Prompt => ACTION_PROMPT,
+      ConflictStrategy::Merge => ACTION_MERGE,
+    }
+  };
+
+  let fresh = action == ACTION_CREATE || backup.is_some();
+  let files = files
+    .iter()
+    .map(|(path, content)| {
+      let file_action = if action == ACTION_SKIP {
+        ACTION_SKIP
+      } else if fresh {
+        ACTION_CREATE
+      } else {
+        match fs::read(Path::new(&destination).join(path)) {
+          Ok(local) if &local == content => ACTION_UNCHANGED,
+          Ok(_) => action,
+          Err(_) => ACTION_CREATE,
+        }
+      };
+
+      PlannedFile {
+        path: path.clone(),
+        action: file_action.to_string(),
+      }
+    })
+    .collect();
+
+  let root = get_local_dir_path();
+
+  PlannedTemplate {
+    name: name.to_string(),
+    template_type: type_name.to_string(),
+    destination: to_relative_path(&root, &destination),
+    backup: backup.map(|backup| to_relative_path(&root, &backup)),
+    action: action.to_string(),
+    dependency: false,
+    files,
+  }
+}
+
+pub fn print_generation_plan(plan: &GenerationPlan, format: PlanFormat) {
+  match format {
+    PlanFormat::Json => match serde_json::to_string_pretty(plan) {
+      Ok(json) => println!("{}", json),
+      Err(e) => eprintln!("{:?}", e.to_string().red()),
+    },
+    PlanFormat::Tree => {
+      println!(
+        "{}",
+        "Generation plan (dry run, nothing is written):".bold()
+      );
+
+      for template in &plan.templates {
+        let dependency = if template.dependency {
+          format!(" {}", "(dependency)".white())
+        } else {
+          String::new()
+        };
+        println!(
+          "{} {} {}{}",
+          format!("[{}]", template.template_type).white(),
+          template.destination.bold(),
+          colorize_action(&template.action),
+          dependency
+        );
+
+        if let Some(backup) = &template.backup {
+          println!("  {} {}", "backup to".white(), backup);
+        }
+
+        print_file_tree(&template.files);
+      }
+    }
+  }
+}
+
+fn colorize_action(action: &str) -> ColoredString {
+  match action {
+    ACTION_CREATE => action.green(),
+    ACTION_OVERWRITE => action.yellow(),
+    ACTION_SKIP | ACTION_UNCHANGED => action.white(),
+    _ => action.cyan(),
+  }
+}
+
+#[derive(Default)]
+struct FileTree<'a> {
+  children: BTreeMap<&'a str, FileTree<'a>>,
+  action: Option<&'a str>,
+}
+
+fn print_file_tree(files: &[PlannedFile]) {
+  let mut root = FileTree::default();
+
+  for file in files {
+    let mut node = &mut root;
+    for part in file.path.split('/') {
+      node = node.children.entry(part).or_default();
+    }
+    node.action = Some(&file.action);
+  }
+
+  print_file_tree_node(&root, "  ");
+}
+
+fn print_file_tree_node(node: &FileTree, prefix: &str) {
+  let count = node.children.len();
+
+  for (index, (name, child)) in node.children.iter().enumerate() {
+    let last = index + 1 == count;
+    let branch = if last { "└── " } else { "├── " };
+
+    match child.action {
+      Some(action) => println!("{}{}{} {}", prefix, branch, name, colorize_action(action)),
+      None => println!("{}{}{}", prefix, branch, name),
+    }
+
+    let next_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+    print_file_tree_node(child, &next_prefix);
+  }
+}
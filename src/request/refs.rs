@@ -0,0 +1,151 @@
+use colored::Colorize;
+use dialoguer::{console::Term, theme::ColorfulTheme, Select};
+use serde::Deserialize;
+
+use crate::helper::ConfigFile;
+
+use super::request::{get_config, request_github_api_with_status};
+
+pub const REF_BRANCH: &str = "branch";
+pub const REF_TAG: &str = "tag";
+
+const REFS_PER_PAGE: usize = 100;
+
+/**
+ * 模板仓库中的分支或 tag
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct RemoteRef {
+  pub name: String,
+  /**
+   * branch | tag
+   */
+  pub ref_type: String,
+  pub commit: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubRefItem {
+  name: String,
+  commit: GithubRefCommit,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubRefCommit {
+  sha: String,
+}
+
+/**
+ * 分页获取仓库的所有分支
+ */
+pub async fn fetch_remote_branches(config: &ConfigFile) -> Result<Vec<RemoteRef>, String> {
+  fetch_paginated_refs(config, &config.get_remote_branches_url(), REF_BRANCH).await
+}
+
+/**
+ * 分页获取仓库的所有 tag，包括 button@1.4.0 形式的模板版本 tag
+ */
+pub async fn fetch_remote_tags(config: &ConfigFile) -> Result<Vec<RemoteRef>, String> {
+  fetch_paginated_refs(config, &config.get_remote_tags_url(), REF_TAG).await
+}
+
+async fn fetch_paginated_refs(
+  config: &ConfigFile,
+  url: &str,
+  ref_type: &str,
+) -> Result<Vec<RemoteRef>, String> {
+  let mut refs = vec![];
+  let mut page = 1;
+
+  loop {
+    let uri = format!("{}?per_page={}&page={}", url, REFS_PER_PAGE, page);
+    let (status, body) =
+      request_github_api_with_status(&uri, &config.github_api_token, None).await?;
+    if status != 200 {
+      return Err(format!("Request {} failure, code: {}", uri, status));
+    }
+
+    let list = serde_json::from_slice::<Vec<GithubRefItem>>(&body).map_err(|e| e.to_string())?;
+    let count = list.len();
+    refs.extend(list.into_iter().map(|item| RemoteRef {
+      name: item.name,
+      ref_type: ref_type.to_string(),
+      commit: item.commit.sha,
+    }));
+
+    if count < REFS_PER_PAGE {
+      break;
+    }
+    page += 1;
+  }
+
+  Ok(refs)
+}
+
+/**
+ * 模板仓库的所有分支和 tag，分支在前
+ */
+pub async fn fetch_remote_refs(config: &ConfigFile) -> Result<Vec<RemoteRef>, String> {
+  let (branches, tags) = futures::join!(fetch_remote_branches(config), fetch_remote_tags(config));
+  let mut refs = branches?;
+  refs.extend(tags?);
+
+  Ok(refs)
+}
+
+pub async fn list_remote_refs() -> Result<Vec<RemoteRef>, String> {
+  let config = get_config();
+  let refs = fetch_remote_refs(&config).await?;
+
+  for item in &refs {
+    let target = if item.ref_type == REF_BRANCH && item.name == config.target_branch {
+      " (target branch)".green()
+    } else {
+      "".green()
+    };
+
+    println!(
+      "{} {} ----> {}{}",
+      format!("[{}]", item.ref_type).white(),
+      item.name,
+      &item.commit[..item.commit.len().min(7)],
+      target
+    );
+  }
+
+  Ok(refs)
+}
+
+/**
+ * 从分支和 tag 中选择一个 ref，默认选中 target_branch，取消选择时为 None
+ */
+pub async fn select_remote_ref() -> Result<Option<String>, String> {
+  let config = get_config();
+  let refs = fetch_remote_refs(&config).await?;
+
+  if refs.is_empty() {
+    return Err(format!(
+      "There is no branch or tag in {}!",
+      config.get_repo_full_name()
+    ));
+  }
+
+  let items: Vec<String> = refs
+    .iter()
+    .map(|item| format!("[{}] {}", item.ref_type, item.name))
+    .collect();
+  let default = refs
+    .iter()
+    .position(|item| item.ref_type == REF_BRANCH && item.name == config.target_branch)
+    .unwrap_or(0);
+
+  let selection = Select::with_theme(&ColorfulTheme::default())
+    .with_prompt("Select a branch or tag")
+    .items(&items)
+    .default(default)
+    .interact_on_opt(&Term::stderr())
+    .map_err(|e| e.to_string())?;
+
+  Ok(selection.map(|index| refs[index].name.clone()))
+}
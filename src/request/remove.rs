@@ -0,0 +1,240 @@
+use std::{
+  collections::BTreeSet,
+  fs,
+  path::{Component, Path, PathBuf},
+};
+
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Confirm, MultiSelect};
+
+use crate::helper::lock::{to_relative_path, LockFile, LockedTemplate};
+
+use super::{
+  request::{fetch_remote_yaml, get_config, get_local_dir_path},
+  resolve::get_all_dependencies,
+  RemoteYaml,
+};
+
+/**
+ * 删除已安装的模板目录，yes 为 true 时跳过确认并一并删除孤立的依赖
+ * 返回被删除的目录
+ */
+pub async fn remove_template(
+  type_name: &str,
+  name: String,
+  yes: bool,
+) -> Result<Vec<String>, String> {
+  let config = get_config();
+  let mut lock_file = LockFile::read_from_local()?;
+
+  let default_path = check_template_name(&name)
+    .and_then(|_| config.get_local_dir(type_name, &name, &name, None, None))
+    .map(|local_dir| to_relative_path(&get_local_dir_path(), &local_dir));
+  let target = lock_file
+    .templates
+    .iter()
+    .filter(|item| item.template_type == type_name)
+    .find(|item| item.path == name || item.name == name || default_path.as_ref() == Ok(&item.path))
+    .cloned();
+
+  let (template_name, path) = match &target {
+    Some(template) => (template.name.clone(), template.path.clone()),
+    None => {
+      // 没有安装记录的目录只按名称在生成目录中查找，删除前必须确认
+      let path = default_path?;
+      let local_path = get_local_path(&path);
+      if !local_path.exists() {
+        return Err(format!("{} is not installed!", path));
+      }
+      check_inside_project(&local_path)?;
+
+      if !yes
+        && !Confirm::with_theme(&ColorfulTheme::default())
+          .with_prompt(format!(
+            "{} is not recorded in wego.lock, remove it anyway?",
+            path
+          ))
+          .default(false)
+          .interact()
+          .map_err(|e| e.to_string())?
+      {
+        return Ok(vec![]);
+      }
+
+      (name.clone(), path)
+    }
+  };
+
+  let manifest = fetch_remote_yaml(&config.github_api_token, &config.target_branch).await?;
+
+  let dependents: Vec<String> = lock_file
+    .templates
+    .iter()
+    .filter(|item| !(item.template_type == type_name && item.path == path))
+    .filter(|item| {
+      get_required_templates(&manifest, item)
+        .contains(&(type_name.to_string(), template_name.clone()))
+    })
+    .map(|item| item.path.clone())
+    .collect();
+
+  if !dependents.is_empty() {
+    println!("{} {} is required by:", "Warning:".red().bold(), path.red());
+    for item in &dependents {
+      println!("  {}", item.yellow());
+    }
+
+    if !yes
+      && !Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt("Remove it anyway?")
+        .default(false)
+        .interact()
+        .map_err(|e| e.to_string())?
+    {
+      return Ok(vec![]);
+    }
+  }
+
+  let mut removed = vec![];
+  remove_local_dir(&mut lock_file, type_name, &path)?;
+  removed.push(path.clone());
+
+  let orphans = find_orphaned_dependencies(
+    &manifest,
+    &lock_file,
+    get_all_dependencies(&manifest, type_name, &template_name),
+  );
+
+  if !orphans.is_empty() {
+    let items: Vec<String> = orphans.iter().map(|item| item.path.clone()).collect();
+    let selected: Vec<usize> = if yes {
+      (0..items.len()).collect()
+    } else {
+      println!(
+        "{}",
+        "These dependencies are no longer required by any installed template:".yellow()
+      );
+      MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Remove orphaned dependencies")
+        .items(&items)
+        .defaults(&vec![true; items.len()])
+        .interact()
+        .map_err(|e| e.to_string())?
+    };
+
+    for index in selected {
+      let orphan = &orphans[index];
+      remove_local_dir(&mut lock_file, &orphan.template_type, &orphan.path)?;
+      removed.push(orphan.path.clone());
+    }
+  }
+
+  Ok(removed)
+}
+
+fn get_local_path(path: &str) -> PathBuf {
+  Path::new(&get_local_dir_path()).join(path)
+}
+
+/**
+ * 没有安装记录时 name 只能是单个目录名，不能为空、. 或 ..，也不能是绝对路径或包含路径分隔符
+ */
+fn check_template_name(name: &str) -> Result<(), String> {
+  let mut components = Path::new(name).components();
+
+  match (components.next(), components.next()) {
+    (Some(Component::Normal(_)), None) if !name.contains(['/', '\\']) => Ok(()),
+    _ => Err(format!("{} is not a valid template name", name)),
+  }
+}
+
+/**
+ * 要删除的目录必须在项目目录内，且不能是项目目录本身
+ */
+fn check_inside_project(local_path: &Path) -> Result<(), String> {
+  let root = fs::canonicalize(get_local_dir_path()).map_err(|e| e.to_string())?;
+  let target = fs::canonicalize(local_path).map_err(|e| e.to_string())?;
+
+  if target.starts_with(&root) && target != root {
+    Ok(())
+  } else {
+    Err(format!(
+      "{} is outside the project, refusing to remove it",
+      local_path.display()
+    ))
+  }
+}
+
+fn remove_local_dir(
+  lock_file: &mut LockFile,
+  template_type: &str,
+  path: &str,
+) -> Result<(), String> {
+  let local_path = get_local_path(path);
+
+  if local_path.exists() {
+    check_inside_project(&local_path)?;
+    fs::remove_dir_all(&local_path).map_err(|e| e.to_string())?;
+  }
+  lock_file.remove(template_type, path);
+  lock_file.write_to_local()?;
+
+  println!("{}, Remove done!", path.green());
+
+  Ok(())
+}
+
+/**
+ * 模板直接和间接依赖的所有模板 (type, name)
+ */
+fn get_required_templates(
+  manifest: &RemoteYaml,
+  template: &LockedTemplate,
+) -> Vec<(String, String)> {
+  get_all_dependencies(manifest, &template.template_type, &template.name)
+}
+
+/**
+ * 被删除模板的所有直接和间接依赖中，作为依赖安装且不再被其他已安装模板需要的组件
+ */
+fn find_orphaned_dependencies(
+  manifest: &RemoteYaml,
+  lock_file: &LockFile,
+  all_deps: Vec<(String, String)>,
+) -> Vec<LockedTemplate> {
+  if all_deps.is_empty() {
+    return vec![];
+  }
+
+  let get_key = |item: &LockedTemplate| (item.template_type.clone(), item.name.clone());
+  let is_candidate = |item: &LockedTemplate| item.dependency && all_deps.contains(&get_key(item));
+
+  let required: BTreeSet<(String, String)> = lock_file
+    .templates
+    .iter()
+    .filter(|item| !is_candidate(item))
+    .flat_map(|item| get_required_templates(manifest, item))
+    .collect();
+
+  lock_file
+    .templates
+    .iter()
+    .filter(|item| is_candidate(item) && !required.contains(&get_key(item)))
+    .cloned()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn accepts_only_single_directory_names() {
+    assert!(check_template_name("button").is_ok());
+    assert!(check_template_name("my-button.v2").is_ok());
+
+    for name in ["", ".", "..", "/", "/etc", "src/button", "..\\x", "a\\b"] {
+      assert!(check_template_name(name).is_err(), "{}", name);
+    }
+  }
+}
@@ -1,24 +1,42 @@
 use async_recursion::async_recursion;
-use std::{fs, path::Path, sync::Arc, time::Instant, vec};
+use std::{collections::BTreeMap, path::Path, sync::Arc, time::Instant, vec};
 
 use colored::Colorize;
-use constants::FILE_MARKER;
-use dialoguer::{console::Term, theme::ColorfulTheme, Input, Select};
+use dialoguer::{console::Term, theme::ColorfulTheme, FuzzySelect, Input, MultiSelect};
 
-use hyper::header::{AUTHORIZATION, USER_AGENT};
-use hyper::{Body, Request};
+use hyper::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
+use hyper::{Body, HeaderMap, Request};
 
-use crate::request::find_all_deps;
+use crate::helper::config::{get_project_root, replace_config_file};
+use crate::helper::conflict::write_template_files;
+use crate::helper::lock::{
+  git_blob_sha, hash_contents, record_installed_templates, to_relative_path, LockFile,
+  LockedTemplate,
+};
+use crate::helper::utils::encode_query_value;
+use crate::helper::ConfigFile;
+use crate::request::plan::{
+  plan_template_files, print_generation_plan, GenerationPlan, PlannedTemplate,
+};
+use crate::request::resolve::{resolve_template_dependencies, ResolvedTemplate};
+use crate::request::version::{
+  format_resolved_version, parse_template_spec, resolve_pinned_revision, resolve_spec_revision,
+};
 use crate::CLIENT;
 use crate::{
-  constants,
-  request::{ContentsResponse, RemoteYaml},
-  TemplateType, CONFIG_FILE,
+  request::{types::TypeConfig, ConfigYaml, ContentsResponse, RemoteYaml},
+  ConflictStrategy, GenerateOptions, TemplateType, CONFIG_FILE,
 };
 
-use super::RemoteGithubDir;
+use super::{schema::parse_remote_yaml, RemoteGithubDir};
 
-pub async fn get_remote_yaml_config(t_type: TemplateType) {
+/**
+ * 请求远程 wego.yaml 并生成选择的模板，dry run 时返回生成计划
+ */
+pub async fn get_remote_yaml_config(
+  type_name: &str,
+  options: GenerateOptions,
+) -> Option<GenerationPlan> {
   let uri;
   let token;
 
@@ -28,9 +46,12 @@ pub async fn get_remote_yaml_config(t_type: TemplateType) {
 
     let url: String = config.get_remote_yaml_url();
 
-    let target_branch = config.target_branch.clone();
+    let target_branch = options
+      .git_ref
+      .clone()
+      .unwrap_or_else(|| config.target_branch.clone());
 
-    uri = format!("{}?{}={}", &url, "ref", &target_branch);
+    uri = format!("{}?ref={}", &url, encode_query_value(&target_branch));
     token = config.github_api_token.clone();
   }
 
@@ -52,87 +73,298 @@ pub async fn get_remote_yaml_config(t_type: TemplateType) {
             .expect("Parse error!!");
           let content_res = serde_json::from_slice::<ContentsResponse>(&body_bytes);
           match content_res {
-            Ok(content) => {
-              show_templates_by_type(content, t_type).await;
-            }
+            Ok(content) => show_templates_by_type(content, type_name, &options).await,
             Err(_) => {
-              println!(
-                "{} Request url: {}",
-                "There is no wego.yaml in your repo!".red(),
-                uri
+              print_message(
+                &options,
+                format!(
+                  "{} Request url: {}",
+                  "There is no wego.yaml in your repo!".red(),
+                  uri
+                ),
               );
+              None
             }
           }
         }
         Err(e) => {
-          println!("{:?}", e.to_string().red());
+          print_message(&options, format!("{:?}", e.to_string().red()));
+          None
         }
       }
     }
     Err(e) => {
-      println!("{:?}", e.to_string().red());
+      print_message(&options, format!("{:?}", e.to_string().red()));
+      None
     }
   }
 }
 
-pub async fn show_templates_by_type(content: ContentsResponse, t_type: TemplateType) {
+/**
+ * json 格式的 dry run 时 stdout 只输出生成计划，其他提示和错误输出到 stderr
+ */
+fn print_message(options: &GenerateOptions, message: String) {
+  if options.is_json_plan() {
+    eprintln!("{}", message);
+  } else {
+    println!("{}", message);
+  }
+}
+
+fn print_error(options: &GenerateOptions, e: &str) {
+  print_message(options, format!("{} {}", "Error:".red().bold(), e.red()));
+}
+
+pub async fn show_templates_by_type(
+  content: ContentsResponse,
+  type_name: &str,
+  options: &GenerateOptions,
+) -> Option<GenerationPlan> {
   let target_branch;
   let token;
   {
     let arc_file = Arc::clone(&CONFIG_FILE);
     let config = arc_file.read().unwrap();
-    target_branch = config.target_branch.clone();
+    target_branch = options
+      .git_ref
+      .clone()
+      .unwrap_or_else(|| config.target_branch.clone());
     token = config.github_api_token.clone();
   }
-  let content_string = content.decode_base64_to_string().unwrap();
-  let info: RemoteYaml = serde_yaml::from_str(&content_string).unwrap();
-
-  match t_type {
-    TemplateType::Pages => {
-      let vec_pages = info.get_page_templates_items_by_type(t_type);
-      let index = show_selection(&vec_pages);
-      if index != 99999 {
-        select_string_items(info.pages[index].name.clone(), t_type).await;
-        // let deps = &info.pages[index].dependencies;
-
-        if let Some(deps) = &info.pages[index].dependencies {
-          if deps.len() > 0 {
-            let all = find_all_deps(deps.clone(), info.components.clone());
-            println!("{} {:?}", "Start loading dependencies ---->".green(), &deps);
-
-            download_components_to_local(all, token, &target_branch).await;
-          }
+  let info = match content
+    .decode_base64_to_string()
+    .map_err(|e| e.to_string())
+    .and_then(|content_string| parse_remote_yaml(&content_string))
+  {
+    Ok(info) => info,
+    Err(e) => {
+      print_error(options, &e);
+      return None;
+    }
+  };
+
+  let template_type = match info.get_type(type_name) {
+    Some(template_type) => template_type,
+    None => {
+      print_error(options, &format!("unknown template type {}", type_name));
+      return None;
+    }
+  };
+
+  if template_type.templates.is_empty() {
+    print_message(
+      options,
+      format!("There is no {} template in wego.yaml!", type_name)
+        .yellow()
+        .to_string(),
+    );
+    return None;
+  }
+
+  let revision = if options.git_ref.is_some() {
+    resolve_pinned_revision(&token, &target_branch).await
+  } else {
+    resolve_revision(&token, &target_branch).await
+  };
+  let mut plan = GenerationPlan::default();
+
+  let spec = options.template.as_deref().map(parse_template_spec);
+  let index = match spec {
+    Some(spec) => {
+      let name = spec.name;
+      match template_type
+        .templates
+        .iter()
+        .position(|item| item.name == name)
+      {
+        Some(index) => index,
+        None => {
+          print_error(
+            options,
+            &format!("{} is not declared in {}", name, type_name),
+          );
+          return None;
         }
       }
     }
-    TemplateType::Components => {
-      let vec_coms = info.get_page_templates_items_by_type(t_type);
-      let index = show_selection(&vec_coms);
-      if index != 99999 {
-        select_string_items(info.components[index].name.clone(), t_type).await;
-
-        if let Some(deps) = &info.components[index].dependencies {
-          if deps.len() > 0 {
-            let all = find_all_deps(deps.clone(), info.components.clone());
-            println!("{} {:?}", "Start loading dependencies ---->".green(), &deps);
-
-            download_components_to_local(all, token, &target_branch).await;
+    None => show_selection(
+      &template_type.get_label(),
+      &template_type.get_output_string_vec(),
+    ),
+  };
+  if index != 99999 {
+    let template = &template_type.templates[index];
+    let template_revision = match resolve_spec_revision(
+      &token,
+      type_name,
+      &template.name,
+      spec.and_then(|spec| spec.version),
+      spec.and_then(|spec| spec.git_ref),
+      &revision,
+      template.version.as_deref(),
+    )
+    .await
+    {
+      Ok(template_revision) => {
+        print_resolved_version(options, type_name, &template.name, &template_revision);
+        template_revision
+      }
+      Err(e) => {
+        print_error(options, &e);
+        return None;
+      }
+    };
+    let selected = match select_optional_dependencies(template, options) {
+      Ok(selected) => selected,
+      Err(e) => {
+        print_error(options, &e);
+        return None;
+      }
+    };
+    for item in template.get_unknown_selections(&selected) {
+      print_warning(options, &item);
+    }
+    let optional = template.get_selected_dependencies(&selected);
+    let mut answers = options.get_answers();
+    if !selected.is_empty() {
+      answers.insert(String::from("features"), selected.join(","));
+    }
+    let dependencies =
+      match resolve_template_dependencies(&info, type_name, &template.name, &optional) {
+        Ok(dependency_plan) => {
+          for warning in &dependency_plan.warnings {
+            print_warning(options, warning);
+          }
+          if dependency_plan.has_dependencies() && !options.is_json_plan() {
+            dependency_plan.print_tree();
           }
+          dependency_plan.get_dependency_templates()
         }
+        Err(_) => vec![],
+      };
+
+    if type_name == TemplateType::Project.dir_name() {
+      // 项目先创建，再把依赖安装到新项目中
+      let (planned, local_path) = select_string_items(
+        template.name.clone(),
+        template_type.get_destination(template),
+        &template_type,
+        &template_revision,
+        answers.clone(),
+        options,
+      )
+      .await;
+      plan.templates.extend(planned);
+
+      if let (Some(local_path), false) = (local_path, dependencies.is_empty()) {
+        let mut project_config = get_config();
+        project_config.project_root = local_path;
+        project_config.destinations.clear();
+        let config = replace_config_file(project_config);
+
+        plan.templates.extend(
+          download_dependencies_to_local(dependencies, token, &info, &revision, options).await,
+        );
+        replace_config_file(config);
       }
-    }
-    TemplateType::Project => {
-      let vec_coms = info.get_page_templates_items_by_type(t_type);
-      let index = show_selection(&vec_coms);
-      if index != 99999 {
-        select_string_items(info.projects[index].name.clone(), t_type).await;
+    } else {
+      // 按拓扑顺序先安装依赖，再安装选择的模板
+      if !dependencies.is_empty() {
+        if !options.is_dry_run() {
+          println!("{}", "Start loading dependencies ---->".green());
+        }
+        plan.templates.extend(
+          download_dependencies_to_local(dependencies, token, &info, &revision, options).await,
+        );
       }
+
+      let (planned, _) = select_string_items(
+        template.name.clone(),
+        template_type.get_destination(template),
+        &template_type,
+        &template_revision,
+        answers.clone(),
+        options,
+      )
+      .await;
+      plan.templates.extend(planned);
     }
   }
+
+  if options.is_dry_run() {
+    print_generation_plan(&plan, options.get_plan_format());
+    return Some(plan);
+  }
+
+  None
+}
+
+fn print_warning(options: &GenerateOptions, warning: &str) {
+  print_message(
+    options,
+    format!("{} {}", "Warning:".yellow().bold(), warning.yellow()),
+  );
+}
+
+/**
+ * 按版本约束解析出版本时输出解析结果
+ */
+fn print_resolved_version(
+  options: &GenerateOptions,
+  type_name: &str,
+  name: &str,
+  revision: &TemplateRevision,
+) {
+  if !revision.version_req.is_empty() {
+    print_message(options, format_resolved_version(type_name, name, revision));
+  }
 }
 
-pub fn show_selection(items: &Vec<String>) -> usize {
-  let selection = Select::with_theme(&ColorfulTheme::default())
+/**
+ * 选择要安装的功能组和可选依赖，返回选择的名称，由 get_selected_dependencies 展开
+ * options 中设置了 features 时直接使用，不再询问
+ */
+fn select_optional_dependencies(
+  template: &ConfigYaml,
+  options: &GenerateOptions,
+) -> Result<Vec<String>, String> {
+  if let Some(features) = &options.features {
+    return Ok(features.clone());
+  }
+  if !template.has_optional_dependencies() {
+    return Ok(vec![]);
+  }
+
+  let mut choices: Vec<String> = vec![];
+  let mut items: Vec<String> = vec![];
+  for (feature, deps) in template.features.iter().flatten() {
+    choices.push(feature.clone());
+    items.push(format!("feature {} ----> {}", feature, deps.join(", ")));
+  }
+  for dep in template.optional_dependencies.iter().flatten() {
+    choices.push(dep.clone());
+    items.push(dep.clone());
+  }
+
+  let selected = MultiSelect::with_theme(&ColorfulTheme::default())
+    .with_prompt("Select optional dependencies(space to select, enter to confirm)")
+    .items(&items)
+    .interact_on(&Term::stderr())
+    .map_err(|e| e.to_string())?;
+  Ok(
+    selected
+      .into_iter()
+      .map(|index| choices[index].clone())
+      .collect(),
+  )
+}
+
+/**
+ * 可以输入关键字模糊搜索的选择列表，取消选择时返回 99999
+ */
+pub fn show_selection(prompt: &str, items: &[String]) -> usize {
+  let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+    .with_prompt(prompt)
     .items(items)
     .default(0)
     .interact_on_opt(&Term::stderr());
@@ -144,333 +376,598 @@ pub fn show_selection(items: &Vec<String>) -> usize {
   }
 }
 
-pub async fn select_string_items(this_page_name: String, t_type: TemplateType) {
+/**
+ * 生成选择的模板，返回 dry run 的计划和生成的本地目录
+ */
+pub async fn select_string_items(
+  this_page_name: String,
+  destination: Option<String>,
+  template_type: &TypeConfig,
+  revision: &TemplateRevision,
+  mut answers: BTreeMap<String, String>,
+  options: &GenerateOptions,
+) -> (Option<PlannedTemplate>, Option<String>) {
   let page_name = &this_page_name;
 
-  let request_url;
-  let tb;
-  let gt;
-
-  {
-    let arc_file = Arc::clone(&CONFIG_FILE);
-    let config = arc_file.read().unwrap();
-
-    request_url = config.get_remote_template_url(t_type, page_name);
-    tb = config.target_branch.clone();
-
-    gt = config.github_api_token.clone();
-  }
+  let config = get_config();
+  let source = template_type.get_source();
+  let request_url = config.get_remote_template_url(&source, page_name);
+  let gt = config.github_api_token.clone();
   let mut final_file_name: String = page_name.clone();
-  if let Ok(custom_name) = Input::new()
+  if let Ok(custom_name) = Input::<String>::new()
     .with_prompt("Custom file name(Not required)")
     .allow_empty(true)
     .interact_text()
   {
-    if custom_name == "" {
+    if custom_name.is_empty() {
       final_file_name = page_name.clone();
     } else {
       final_file_name = custom_name;
     }
   }
   let token: String = String::from(&gt);
-  let res = fetch_remote_dir(request_url.clone(), &token, &tb).await;
+  let tb = revision.fetch_ref();
+  let start_time = Instant::now();
 
-  if res.len() > 0 {
-    let local_path = create_dir_to_local(final_file_name, t_type);
-
-    let start_time = Instant::now();
+  let files = match fetch_template_files(request_url.clone(), &token, tb).await {
+    Ok(files) => files,
+    Err(e) => {
+      print_message(
+        options,
+        format!(
+          "{} {} {}",
+          "Error: request templates failure! ".red(),
+          &request_url,
+          e
+        ),
+      );
+      return (None, None);
+    }
+  };
+
+  let local_path = match config.get_local_dir(
+    &template_type.name,
+    page_name,
+    &final_file_name,
+    options.dest.as_deref(),
+    destination.as_deref(),
+  ) {
+    Ok(local_path) => local_path,
+    Err(e) => {
+      print_error(options, &e);
+      return (None, None);
+    }
+  };
+  let strategy = options.get_conflict_strategy();
+
+  if options.is_dry_run() {
+    let planned = plan_template_files(
+      page_name,
+      &template_type.name,
+      &local_path,
+      &files,
+      strategy,
+    );
+    return (Some(planned), Some(local_path));
+  }
 
-    run_job(res, local_path, token, request_url.clone(), &tb).await;
+  let base = fetch_merge_base(&template_type.name, &local_path, &token, strategy).await;
 
-    println!("Done in {:?} ms!", start_time.elapsed().as_millis());
-  }
-}
+  match write_template_files(&local_path, &files, strategy, base.as_ref()) {
+    Ok(Some(local_path)) => {
+      println!("Done in {:?} ms!", start_time.elapsed().as_millis());
 
-pub fn get_local_dir(name: String, temp_type: TemplateType) -> String {
-  let curr_path = get_local_dir_path();
-  let type_url: String;
-  match temp_type {
-    TemplateType::Components => type_url = String::from("src/components"),
-    TemplateType::Pages => type_url = String::from("src/pages"),
-    TemplateType::Project => type_url = String::from(""),
+      let mut tree_shas = fetch_template_tree_shas(&source, &token, tb)
+        .await
+        .unwrap_or_default();
+      answers.insert(String::from("name"), get_dir_name(&local_path));
+
+      record_installed_templates(vec![new_locked_template(
+        &template_type.name,
+        &source,
+        page_name,
+        &local_path,
+        answers,
+        &files,
+        revision,
+        tree_shas.remove(page_name).unwrap_or_default(),
+      )]);
+
+      (None, Some(local_path))
+    }
+    Ok(None) => (None, Some(local_path)),
+    Err(e) => {
+      println!("{} {}", "Write template failure:".red(), e);
+      (None, None)
+    }
   }
-  let path_vec = vec![curr_path, type_url, name];
-
-  path_vec.join(FILE_MARKER)
 }
 
-pub fn get_item_url(file_type: TemplateType, file_name: &String) -> String {
+pub fn get_item_url(source: &str, file_name: &str) -> String {
   let arc_config = Arc::clone(&crate::CONFIG_FILE);
   let config = arc_config.read().unwrap();
 
-  config.get_remote_template_url(file_type, &file_name)
+  config.get_remote_template_url(source, file_name)
 }
 
-pub async fn download_components_to_local(
-  coms: Vec<String>,
+/**
+ * 按安装顺序下载依赖，可以是任意类型的模板，限定了版本的依赖按约束解析出的提交下载
+ */
+pub async fn download_dependencies_to_local(
+  deps: Vec<ResolvedTemplate>,
   token: String,
-  target_branch: &String,
-) {
-  if coms.len() > 0 {
+  manifest: &RemoteYaml,
+  revision: &TemplateRevision,
+  options: &GenerateOptions,
+) -> Vec<PlannedTemplate> {
+  let mut plan = vec![];
+
+  if !deps.is_empty() {
     let start_time = Instant::now();
+    let strategy = options.get_conflict_strategy();
+    let config = get_config();
     let mut async_tasks = vec![];
 
-    for item in coms {
-      let local_path = get_local_dir(item.clone(), TemplateType::Components);
-      let request_url = get_item_url(TemplateType::Components, &item);
+    for dep in deps {
+      let (type_name, item) = (dep.template_type, dep.name);
+      let dep_revision = match resolve_spec_revision(
+        &token,
+        &type_name,
+        &item,
+        dep.version.as_deref(),
+        dep.git_ref.as_deref(),
+        revision,
+        manifest.get_version(&type_name, &item).as_deref(),
+      )
+      .await
+      {
+        Ok(dep_revision) => {
+          print_resolved_version(options, &type_name, &item, &dep_revision);
+          dep_revision
+        }
+        Err(e) => {
+          print_error(options, &e);
+          continue;
+        }
+      };
+      let local_path = match config.get_local_dir(
+        &type_name,
+        &item,
+        &item,
+        None,
+        manifest.get_destination(&type_name, &item).as_deref(),
+      ) {
+        Ok(local_path) => local_path,
+        Err(e) => {
+          print_error(options, &e);
+          continue;
+        }
+      };
+      let source = manifest.get_source(&type_name);
+      let request_url = get_item_url(&source, &item);
       let p = Path::new(&local_path);
 
-      if p.exists() {
+      if p.exists() && matches!(strategy, ConflictStrategy::Skip) && !options.is_dry_run() {
         println!(
           "{} {} is already existed!",
           "Warning:".red().bold(),
           local_path.red()
         );
       } else {
-        let res = fetch_remote_dir(request_url.clone(), &token, &target_branch).await;
-        if res.len() > 0 {
-          let local_path = create_dir_to_local(item.clone(), TemplateType::Components);
-          let handle = run_job(
-            res,
-            local_path,
-            token.to_string(),
-            request_url.clone(),
-            &target_branch,
+        let token = token.to_string();
+        let handle = async move {
+          let files = fetch_template_files(request_url, &token, dep_revision.fetch_ref()).await;
+          (type_name, source, item, local_path, dep_revision, files)
+        };
+        async_tasks.push(handle);
+      }
+    }
+    let fetched = futures::future::join_all(async_tasks).await;
+
+    let mut templates = vec![];
+    for (type_name, source, item, local_path, dep_revision, files) in fetched {
+      let files = match files {
+        Ok(files) => files,
+        Err(e) => {
+          print_message(
+            options,
+            format!(
+              "{} {} {}",
+              "Error: request templates failure! ".red(),
+              item,
+              e
+            ),
           );
-          async_tasks.push(handle);
+          continue;
+        }
+      };
+
+      if options.is_dry_run() {
+        let mut planned = plan_template_files(&item, &type_name, &local_path, &files, strategy);
+        planned.dependency = true;
+        plan.push(planned);
+        continue;
+      }
+
+      let base = fetch_merge_base(&type_name, &local_path, &token, strategy).await;
+      match write_template_files(&local_path, &files, strategy, base.as_ref()) {
+        Ok(Some(local_path)) => {
+          templates.push((type_name, source, item, local_path, dep_revision, files))
+        }
+        Ok(None) => {}
+        Err(e) => {
+          println!("{} {}", "Write template failure:".red(), e);
+        }
+      }
+    }
+
+    if !options.is_dry_run() {
+      println!("Done in {:?} ms!", start_time.elapsed().as_millis());
+    }
+
+    if !templates.is_empty() {
+      let mut tree_shas: BTreeMap<(String, String), BTreeMap<String, String>> = BTreeMap::new();
+      let mut locked_templates = vec![];
+
+      for (type_name, source, item, local_path, dep_revision, files) in templates {
+        let key = (source.clone(), dep_revision.fetch_ref().to_string());
+        if !tree_shas.contains_key(&key) {
+          let shas = fetch_template_tree_shas(&source, &token, dep_revision.fetch_ref())
+            .await
+            .unwrap_or_default();
+          tree_shas.insert(key.clone(), shas);
         }
+        let tree = tree_shas
+          .get(&key)
+          .and_then(|shas| shas.get(&item))
+          .cloned()
+          .unwrap_or_default();
+
+        let mut locked = new_locked_template(
+          &type_name,
+          &source,
+          &item,
+          &local_path,
+          options.get_dependency_answers(),
+          &files,
+          &dep_revision,
+          tree,
+        );
+        locked.dependency = true;
+        locked_templates.push(locked);
       }
+
+      record_installed_templates(locked_templates);
     }
-    futures::future::join_all(async_tasks).await;
-    println!("Done in {:?} ms!", start_time.elapsed().as_millis());
   }
-}
 
-pub async fn fetch_repo_yaml_file(t_type: TemplateType) {
-  get_remote_yaml_config(t_type).await;
+  plan
 }
 
-pub async fn fetch_remote_dir(url: String, token: &str, tb: &str) -> RemoteGithubDir {
-  let uri = format!("{}?{}={}", &url, "ref", &tb);
+/**
+ * 合并策略下以 wego.lock 中记录的安装版本作为合并的 base
+ */
+async fn fetch_merge_base(
+  type_name: &str,
+  local_path: &str,
+  token: &str,
+  strategy: ConflictStrategy,
+) -> Option<BTreeMap<String, Vec<u8>>> {
+  if !matches!(strategy, ConflictStrategy::Merge) || !Path::new(local_path).exists() {
+    return None;
+  }
 
-  let req_result = Request::builder()
-    .uri(uri)
-    .method("GET")
-    .header(AUTHORIZATION, format!("Bearer {}", token))
-    .header(USER_AGENT, "wego")
-    .body(Body::empty());
+  let lock_file = LockFile::read_from_local().ok()?;
+  let template = lock_file.find(
+    type_name,
+    &to_relative_path(&get_local_dir_path(), local_path),
+  )?;
+  if template.commit.is_empty() {
+    return None;
+  }
 
-  match req_result {
-    Ok(req) => {
-      let fetch_res = CLIENT.request(req).await.expect("");
+  let url = get_config().get_remote_template_url(template.get_source(), &template.name);
+  fetch_template_files(url, token, &template.commit)
+    .await
+    .ok()
+}
 
-      let body_bytes = hyper::body::to_bytes(fetch_res.into_body())
-        .await
-        .expect("Parse error!!");
-      let content_res = serde_json::from_slice::<RemoteGithubDir>(&body_bytes);
+fn get_dir_name(local_path: &str) -> String {
+  Path::new(local_path)
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default()
+}
 
-      match content_res {
-        Ok(content) => {
-          return content;
-        }
-        Err(e) => {
-          println!("{:?}", e.to_string().red());
-        }
-      }
+/**
+ * 一次生成所使用的远程版本：分支名或 tag 以及解析出的 commit sha
+ */
+#[derive(Debug, Clone, Default)]
+pub struct TemplateRevision {
+  pub git_ref: String,
+  pub commit: String,
+  /**
+   * 按版本约束解析出的模板版本，未指定版本时为空
+   */
+  pub version: String,
+  pub version_req: String,
+  /**
+   * 通过 ref 指定的版本，不跟随 target_branch 升级
+   */
+  pub pinned: bool,
+}
+
+impl TemplateRevision {
+  /**
+   * 优先使用 commit 请求文件，保证同一次生成的所有文件来自同一个提交
+   */
+  pub fn fetch_ref(&self) -> &str {
+    if self.commit.is_empty() {
+      &self.git_ref
+    } else {
+      &self.commit
     }
+  }
+}
+
+pub async fn resolve_revision(token: &str, git_ref: &str) -> TemplateRevision {
+  let commit = match fetch_commit_sha(token, git_ref).await {
+    Ok(commit) => commit,
     Err(e) => {
-      println!("{:?}", e.to_string().red());
+      // 输出到 stderr，json 格式的 dry run 时不会混入生成计划
+      eprintln!(
+        "{} Resolve commit of {} failure: {}",
+        "Warning:".red().bold(),
+        git_ref.red(),
+        e
+      );
+      String::new()
     }
+  };
+
+  TemplateRevision {
+    git_ref: git_ref.to_string(),
+    commit,
+    ..Default::default()
   }
-  return vec![];
 }
 
-pub fn get_local_dir_path() -> String {
-  let binding = std::env::current_dir()
-    .unwrap()
-    .to_str()
-    .unwrap()
-    .to_string();
+#[allow(clippy::too_many_arguments)]
+fn new_locked_template(
+  type_name: &str,
+  source: &str,
+  name: &str,
+  local_path: &str,
+  answers: BTreeMap<String, String>,
+  files: &BTreeMap<String, Vec<u8>>,
+  revision: &TemplateRevision,
+  tree: String,
+) -> LockedTemplate {
+  let config = get_config();
+
+  LockedTemplate {
+    name: name.to_string(),
+    template_type: type_name.to_string(),
+    source: if source == type_name {
+      String::new()
+    } else {
+      source.to_string()
+    },
+    repo: config.get_repo_full_name(),
+    git_ref: revision.git_ref.clone(),
+    commit: revision.commit.clone(),
+    version: revision.version.clone(),
+    version_req: revision.version_req.clone(),
+    pinned: revision.pinned,
+    tree,
+    path: to_relative_path(&get_local_dir_path(), local_path),
+    dependency: false,
+    answers,
+    files: hash_contents(files),
+  }
+}
+
+pub fn get_config() -> ConfigFile {
+  let arc_file = Arc::clone(&CONFIG_FILE);
+  let config = arc_file.read().unwrap();
 
-  binding
+  config.clone()
 }
 
-pub fn create_dir_to_local(name: String, temp_type: TemplateType) -> String {
-  let curr_path = get_local_dir_path();
-  let type_url: String;
-  match temp_type {
-    TemplateType::Components => type_url = String::from("src/components"),
-    TemplateType::Pages => type_url = String::from("src/pages"),
-    TemplateType::Project => type_url = String::from(""),
+pub async fn request_github_api(
+  uri: &str,
+  token: &str,
+  accept: Option<&str>,
+) -> Result<Vec<u8>, String> {
+  let (status, body) = request_github_api_with_status(uri, token, accept).await?;
+
+  if status == 200 {
+    Ok(body)
+  } else {
+    Err(format!("Request {} failure, code: {}", uri, status))
   }
+}
 
-  let path_vec = vec![curr_path, type_url, name];
-  if let Err(e) = fs::create_dir_all(path_vec.join(FILE_MARKER)) {
-    println!("Create dir failure: {:#?}", e);
+/**
+ * 返回状态码和响应内容，由调用方根据状态码判断 401、404 等情况
+ */
+pub async fn request_github_api_with_status(
+  uri: &str,
+  token: &str,
+  accept: Option<&str>,
+) -> Result<(u16, Vec<u8>), String> {
+  let (status, _, body) = request_github_api_with_headers(uri, token, accept).await?;
+
+  Ok((status, body))
+}
+
+/**
+ * 同时返回响应头，用于读取 x-oauth-scopes、x-ratelimit-* 等信息
+ * token 为空时不发送 Authorization，以匿名身份访问公开仓库
+ */
+pub async fn request_github_api_with_headers(
+  uri: &str,
+  token: &str,
+  accept: Option<&str>,
+) -> Result<(u16, HeaderMap, Vec<u8>), String> {
+  let mut builder = Request::builder()
+    .uri(uri)
+    .method("GET")
+    .header(USER_AGENT, "wego");
+
+  if !token.is_empty() {
+    builder = builder.header(AUTHORIZATION, format!("Bearer {}", token));
+  }
+  if let Some(accept) = accept {
+    builder = builder.header(ACCEPT, accept);
   }
 
-  let path_vec: Vec<String> = path_vec
-    .iter()
-    .filter(|item| !item.is_empty())
-    .cloned()
-    .collect();
+  let req = builder.body(Body::empty()).map_err(|e| e.to_string())?;
+  let res = CLIENT.request(req).await.map_err(|e| e.to_string())?;
+  let status = res.status().as_u16();
+  let headers = res.headers().clone();
+  let body_bytes = hyper::body::to_bytes(res.into_body())
+    .await
+    .map_err(|e| e.to_string())?;
+
+  Ok((status, headers, body_bytes.to_vec()))
+}
+
+/**
+ * 获取并解析远程仓库中的 wego.yaml
+ */
+pub async fn fetch_remote_yaml(token: &str, git_ref: &str) -> Result<RemoteYaml, String> {
+  parse_remote_yaml(&fetch_remote_yaml_content(token, git_ref).await?)
+}
+
+/**
+ * 获取远程仓库中 wego.yaml 的原始内容
+ */
+pub async fn fetch_remote_yaml_content(token: &str, git_ref: &str) -> Result<String, String> {
+  let uri = format!(
+    "{}?ref={}",
+    get_config().get_remote_yaml_url(),
+    encode_query_value(git_ref)
+  );
+  let body = request_github_api(&uri, token, None)
+    .await
+    .map_err(|e| format!("There is no wego.yaml in your repo! {}", e))?;
+  let content = serde_json::from_slice::<ContentsResponse>(&body).map_err(|e| e.to_string())?;
+
+  content.decode_base64_to_string().map_err(|e| e.to_string())
+}
+
+pub async fn fetch_commit_sha(token: &str, git_ref: &str) -> Result<String, String> {
+  let url = get_config().get_remote_commit_url(git_ref);
+  let body = request_github_api(&url, token, Some("application/vnd.github.sha")).await?;
+
+  String::from_utf8(body)
+    .map(|sha| sha.trim().to_string())
+    .map_err(|e| e.to_string())
+}
+
+/**
+ * 获取某类模板目录下所有模板的 tree sha，用于判断模板在远程是否有更新
+ */
+pub async fn fetch_template_tree_shas(
+  source: &str,
+  token: &str,
+  tb: &str,
+) -> Result<BTreeMap<String, String>, String> {
+  let url = format!(
+    "{}?ref={}",
+    get_config().get_remote_templates_dir_url(source),
+    encode_query_value(tb)
+  );
+  let body = request_github_api(&url, token, None).await?;
+  let dir = serde_json::from_slice::<RemoteGithubDir>(&body).map_err(|e| e.to_string())?;
+
+  Ok(
+    dir
+      .into_iter()
+      .filter(|item| item.file_type == "dir")
+      .map(|item| (item.name, item.sha))
+      .collect(),
+  )
+}
 
-  path_vec.join(FILE_MARKER)
+/**
+ * 把远程模板目录完整读取到内存中，key 为相对于模板目录的路径
+ */
+pub async fn fetch_template_files(
+  url: String,
+  token: &str,
+  tb: &str,
+) -> Result<BTreeMap<String, Vec<u8>>, String> {
+  let uri = format!("{}?ref={}", &url, encode_query_value(tb));
+
+  fetch_template_dir_files(uri, token.to_string(), String::new()).await
 }
 
-async fn run_job(
-  info: RemoteGithubDir,
-  local_path: String,
+#[async_recursion]
+async fn fetch_template_dir_files(
+  uri: String,
   token: String,
-  _request_path: String,
-  target_branch: &String,
-) {
-  let mut async_tasks = vec![];
-  for data in info {
-    let this_dir_path = vec![local_path.clone(), data.name.clone()].join(FILE_MARKER);
-    if data.file_type == "dir" {
-      let handle = create_local_dir(
-        this_dir_path.clone(),
-        token.to_string(),
-        data.url,
-        target_branch.clone(),
-      );
+  prefix: String,
+) -> Result<BTreeMap<String, Vec<u8>>, String> {
+  let body = request_github_api(&uri, &token, None).await?;
+  let dir = serde_json::from_slice::<RemoteGithubDir>(&body).map_err(|e| e.to_string())?;
+
+  let mut file_tasks = vec![];
+  let mut dir_tasks = vec![];
 
-      async_tasks.push(handle);
+  for data in dir {
+    let path = if prefix.is_empty() {
+      data.name.clone()
     } else {
-      let handle = create_local_file(
-        data.url,
-        this_dir_path.clone(),
-        token.to_string(),
-        target_branch.clone(),
-        data.html_url,
-      );
-      async_tasks.push(handle);
+      format!("{}/{}", prefix, data.name)
+    };
+
+    if data.file_type == "dir" {
+      dir_tasks.push(fetch_template_dir_files(data.url, token.clone(), path));
+    } else {
+      file_tasks.push(fetch_template_file(data.url, token.clone(), path, data.sha));
     }
   }
-  futures::future::join_all(async_tasks).await;
+
+  let (files, dirs) = futures::join!(
+    futures::future::join_all(file_tasks),
+    futures::future::join_all(dir_tasks)
+  );
+
+  let mut result = BTreeMap::new();
+  for file in files {
+    let (path, content) = file?;
+    result.insert(path, content);
+  }
+  for dir in dirs {
+    result.extend(dir?);
+  }
+
+  Ok(result)
 }
 
-#[async_recursion]
-async fn create_local_dir(
-  local_path: String,
+async fn fetch_template_file(
+  uri: String,
   token: String,
-  request_path: String,
-  target_branch: String,
-) {
-  if let Err(e) = fs::create_dir_all(&local_path) {
-    println!("Create dir failure: {:#?}", e);
+  path: String,
+  sha: String,
+) -> Result<(String, Vec<u8>), String> {
+  let body = request_github_api(&uri, &token, None).await?;
+  let content = serde_json::from_slice::<ContentsResponse>(&body).map_err(|e| e.to_string())?;
+  let content_u8 = content.decode_base64_to_u8().map_err(|e| e.to_string())?;
+
+  if git_blob_sha(&content_u8) != sha {
+    return Err(format!("{} is broken, sha mismatch!", path));
   }
-  println!("{}, {}", &local_path.green(), "Create done!");
-  let req_result = Request::builder()
-    .uri(&request_path)
-    .method("GET")
-    .header(AUTHORIZATION, format!("Bearer {}", token))
-    .header(USER_AGENT, "wego")
-    .body(Body::empty());
 
-  match req_result {
-    Ok(req) => {
-      let fetch_res = CLIENT.request(req).await;
-      match fetch_res {
-        Ok(res) => {
-          if res.status() == 200 {
-            let body_bytes = hyper::body::to_bytes(res.into_body())
-              .await
-              .expect("Parse error!!");
-            let content_res = serde_json::from_slice::<RemoteGithubDir>(&body_bytes);
-
-            match content_res {
-              Ok(content) => {
-                futures::join!(run_job(
-                  content,
-                  local_path.clone(),
-                  token.clone(),
-                  request_path.clone(),
-                  &target_branch,
-                ));
-              }
-              Err(e) => {
-                println!("{:?}", e.to_string().red());
-              }
-            }
-          } else {
-            println!(
-              "{} {}",
-              "Request remote dir failure, code: ".red(),
-              res.status().as_str().red().bold()
-            )
-          }
-        }
-        Err(e) => {
-          println!("{:?}", e.to_string().red());
-        }
-      }
-    }
-    Err(e) => {
-      println!("{:?}", e.to_string().red());
-    }
-  }
+  Ok((path, content_u8))
 }
 
-#[async_recursion]
-async fn create_local_file(
-  request_path: String,
-  local_path: String,
-  token: String,
-  _target_branch: String,
-  preview_url: String,
-) {
-  let req_result = Request::builder()
-    .uri(&request_path)
-    .method("GET")
-    .header(AUTHORIZATION, format!("Bearer {}", token))
-    .header(USER_AGENT, "wego")
-    .body(Body::empty());
+pub async fn fetch_repo_yaml_file(type_name: &str, options: GenerateOptions) {
+  get_remote_yaml_config(type_name, options).await;
+}
 
-  match req_result {
-    Ok(req) => {
-      let fetch_res = CLIENT.request(req).await;
-      match fetch_res {
-        Ok(res) => {
-          if res.status() == 200 {
-            let body_bytes = hyper::body::to_bytes(res.into_body())
-              .await
-              .expect("Parse error!!");
-            let content_res = serde_json::from_slice::<ContentsResponse>(&body_bytes);
-
-            match content_res {
-              Ok(content) => {
-                if let Ok(content_u8) = content.decode_base64_to_u8() {
-                  if let Err(e) = fs::write(local_path.clone(), content_u8) {
-                    println!("Write local config file failure: {:#?}", e);
-                  }
-                  if request_path.contains("README") {
-                    println!("{}, {}", &preview_url.white(), "Write done!");
-                  } else {
-                    println!("{}, {}", &local_path.green(), "Write done!");
-                  }
-                }
-              }
-              Err(e) => {
-                println!("{:?}", e.to_string().red());
-              }
-            }
-          } else {
-            println!(
-              "{} {}",
-              "Request remote dir failure, code: ".red(),
-              res.status().as_str().red().bold()
-            )
-          }
-        }
-        Err(e) => {
-          println!("{:?}", e.to_string().red());
-        }
-      }
-    }
-    Err(e) => {
-      println!("{:?}", e.to_string().red());
-    }
-  }
+pub fn get_local_dir_path() -> String {
+  get_project_root()
 }
@@ -0,0 +1,580 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use colored::Colorize;
+
+use crate::TemplateType;
+
+use super::{
+  request::{fetch_remote_yaml, get_config},
+  version::{format_spec_suffix, parse_template_spec},
+  RemoteYaml,
+};
+
+/**
+ * 依赖解析结果中的一个模板
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ResolvedTemplate {
+  pub name: String,
+  pub template_type: String,
+  /**
+   * 安装的原因，例如 page `dashboard` → `chart` → `tooltip`
+   */
+  pub reason: String,
+  /**
+   * 从选择的模板到该模板的依赖路径
+   */
+  pub path: Vec<String>,
+  /**
+   * 直接依赖的模板，components 只有名称，其他类型为 type/name
+   */
+  pub dependencies: Vec<String>,
+  /**
+   * 是否作为依赖安装，选择的模板本身为 false
+   */
+  pub dependency: bool,
+  /**
+   * 依赖声明中的版本约束，例如 ^1.2，未限定版本时为空
+   */
+  pub version: Option<String>,
+  /**
+   * 依赖声明中指定的分支、tag 或 commit sha，例如 button#main
+   */
+  pub git_ref: Option<String>,
+}
+
+impl ResolvedTemplate {
+  /**
+   * 名称之后的版本约束或 ref，例如 @^1.2、#main
+   */
+  pub fn get_spec_suffix(&self) -> String {
+    format_spec_suffix(self.version.as_deref(), self.git_ref.as_deref())
+  }
+}
+
+/**
+ * 按拓扑顺序排列的安装计划，依赖排在依赖它的模板前面，选择的模板在最后
+ */
+#[derive(Debug, Default)]
+pub struct DependencyPlan {
+  pub templates: Vec<ResolvedTemplate>,
+  /**
+   * 未声明的依赖和循环依赖，不影响其余模板的安装
+   */
+  pub warnings: Vec<String>,
+}
+
+impl DependencyPlan {
+  /**
+   * 按安装顺序排列的依赖 (type, name)，不包含选择的模板
+   */
+  pub fn get_dependencies(&self) -> Vec<(String, String)> {
+    self
+      .templates
+      .iter()
+      .filter(|item| item.dependency)
+      .map(|item| (item.template_type.clone(), item.name.clone()))
+      .collect()
+  }
+
+  /**
+   * 按安装顺序排列的依赖，包含版本约束
+   */
+  pub fn get_dependency_templates(&self) -> Vec<ResolvedTemplate> {
+    self
+      .templates
+      .iter()
+      .filter(|item| item.dependency)
+      .cloned()
+      .collect()
+  }
+
+  pub fn has_dependencies(&self) -> bool {
+    self.templates.iter().any(|item| item.dependency)
+  }
+
+  pub fn print_warnings(&self) {
+    for warning in &self.warnings {
+      println!("{} {}", "Warning:".yellow().bold(), warning.yellow());
+    }
+  }
+
+  /**
+   * 以树的形式输出依赖关系，重复出现的模板标记为 (*)
+   */
+  pub fn print_tree(&self) {
+    let root = match self.templates.iter().find(|item| !item.dependency) {
+      Some(root) => root,
+      None => return,
+    };
+    let templates: BTreeMap<(&str, &str), &ResolvedTemplate> = self
+      .templates
+      .iter()
+      .map(|item| ((item.template_type.as_str(), item.name.as_str()), item))
+      .collect();
+
+    println!(
+      "{}",
+      format!("{}/{}", root.template_type, root.name).green()
+    );
+    let mut printed = BTreeSet::new();
+    print_tree_children(root, &templates, "", &mut printed);
+  }
+}
+
+fn print_tree_children<'a>(
+  template: &ResolvedTemplate,
+  templates: &BTreeMap<(&str, &str), &'a ResolvedTemplate>,
+  prefix: &str,
+  printed: &mut BTreeSet<(&'a str, &'a str)>,
+) {
+  let children: Vec<&&ResolvedTemplate> = template
+    .dependencies
+    .iter()
+    .filter_map(|item| templates.get(&split_template_ref(item)))
+    .collect();
+
+  for (index, child) in children.iter().enumerate() {
+    let last = index == children.len() - 1;
+    let key = (child.template_type.as_str(), child.name.as_str());
+    let repeated = !printed.insert(key);
+
+    println!(
+      "{}{} {}{}{}",
+      prefix,
+      if last { "└──" } else { "├──" },
+      format_template_ref(&child.template_type, &child.name),
+      child.get_spec_suffix().white(),
+      if repeated { " (*)".white() } else { "".white() }
+    );
+
+    if !repeated {
+      let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+      print_tree_children(child, templates, &prefix, printed);
+    }
+  }
+}
+
+/**
+ * 解析依赖声明，支持 components/button、pages/login、hooks/useAuth 等带类型的写法
+ * 没有类型或类型未声明时按 components 处理，兼容只写组件名称的依赖
+ * button@^1.2、button#main 形式的版本约束和 ref 会被忽略，由 parse_template_spec 单独解析
+ */
+pub fn parse_dependency(manifest: &RemoteYaml, dep: &str) -> (String, String) {
+  let dep = parse_template_spec(dep).name;
+  if let Some((type_name, name)) = dep.split_once('/') {
+    if manifest.get_type(type_name).is_some() {
+      return (type_name.to_string(), name.to_string());
+    }
+  }
+
+  (
+    TemplateType::Components.dir_name().to_string(),
+    dep.to_string(),
+  )
+}
+
+/**
+ * 依赖的展示形式，components 只显示名称，其他类型为 type/name
+ */
+pub fn format_template_ref(type_name: &str, name: &str) -> String {
+  if type_name == TemplateType::Components.dir_name() {
+    name.to_string()
+  } else {
+    format!("{}/{}", type_name, name)
+  }
+}
+
+fn split_template_ref(template_ref: &str) -> (&str, &str) {
+  match template_ref.split_once('/') {
+    Some((type_name, name)) => (type_name, name),
+    None => (TemplateType::Components.dir_name(), template_ref),
+  }
+}
+
+#[derive(Default)]
+struct ResolveState {
+  stack: Vec<(String, String)>,
+  resolved: BTreeSet<(String, String)>,
+  /**
+   * 选择的可选依赖，只作为选择的模板的直接依赖参与解析
+   */
+  optional: Vec<String>,
+  plan: DependencyPlan,
+}
+
+/**
+ * 解析模板的所有直接和间接依赖，返回拓扑排序后的安装计划
+ * optional 为选择安装的可选依赖，未选择的可选依赖不会被解析
+ */
+pub fn resolve_template_dependencies(
+  manifest: &RemoteYaml,
+  type_name: &str,
+  name: &str,
+  optional: &[String],
+) -> Result<DependencyPlan, String> {
+  if !manifest
+    .get_templates(type_name)
+    .iter()
+    .any(|item| item.name == name)
+  {
+    return Err(format!("{} is not declared in {}", name, type_name));
+  }
+
+  let mut state = ResolveState {
+    optional: optional.to_vec(),
+    ..Default::default()
+  };
+  let root = (type_name.to_string(), name.to_string());
+  visit_template(
+    manifest,
+    &root,
+    vec![name.to_string()],
+    None,
+    None,
+    &mut state,
+  );
+
+  Ok(state.plan)
+}
+
+/**
+ * 模板直接和间接依赖的所有模板 (type, name)，模板不存在时为空
+ */
+pub fn get_all_dependencies(
+  manifest: &RemoteYaml,
+  type_name: &str,
+  name: &str,
+) -> Vec<(String, String)> {
+  resolve_template_dependencies(manifest, type_name, name, &[])
+    .map(|plan| plan.get_dependencies())
+    .unwrap_or_default()
+}
+
+/**
+ * 深度优先遍历，依赖全部加入计划后再加入模板本身，得到拓扑顺序
+ * path 为从选择的模板到当前模板的依赖路径
+ */
+fn visit_template(
+  manifest: &RemoteYaml,
+  template: &(String, String),
+  path: Vec<String>,
+  version: Option<String>,
+  git_ref: Option<String>,
+  state: &mut ResolveState,
+) {
+  if let Some(index) = state.stack.iter().position(|item| item == template) {
+    let mut cycle: Vec<String> = state.stack[index..]
+      .iter()
+      .map(|(type_name, name)| format!("`{}`", format_template_ref(type_name, name)))
+      .collect();
+    cycle.push(format!(
+      "`{}`",
+      format_template_ref(&template.0, &template.1)
+    ));
+    state
+      .plan
+      .warnings
+      .push(format!("dependency cycle: {}", cycle.join(" → ")));
+    return;
+  }
+  if state.resolved.contains(template) {
+    return;
+  }
+
+  let (type_name, name) = template;
+  let mut dependencies = vec![];
+  let mut declared = manifest.get_dependencies(type_name, name);
+  if state.stack.is_empty() {
+    declared.extend(state.optional.clone());
+  }
+
+  state.stack.push(template.clone());
+  for dep in declared {
+    let (dep_type, dep_name) = parse_dependency(manifest, &dep);
+    if !manifest
+      .get_templates(&dep_type)
+      .iter()
+      .any(|item| item.name == dep_name)
+    {
+      state.plan.warnings.push(format!(
+        "{} depends on `{}`, which is not declared in {}",
+        format_reason(&state.stack[0].0, &path),
+        dep,
+        dep_type
+      ));
+      continue;
+    }
+
+    let dep_ref = format_template_ref(&dep_type, &dep_name);
+    let spec = parse_template_spec(&dep);
+    let suffix = spec.get_suffix();
+    if let Some(resolved) = state
+      .plan
+      .templates
+      .iter()
+      .find(|item| item.template_type == dep_type && item.name == dep_name)
+    {
+      // 先解析到的版本生效，之后不同的版本约束或 ref 只给出警告
+      if !suffix.is_empty() && resolved.get_spec_suffix() != suffix {
+        state.plan.warnings.push(format!(
+          "{} requires `{}{}`, but it is already resolved as `{}{}`",
+          format_reason(&state.stack[0].0, &path),
+          dep_ref,
+          suffix,
+          dep_ref,
+          match resolved.get_spec_suffix().as_str() {
+            "" => String::from("@*"),
+            resolved_suffix => resolved_suffix.to_string(),
+          }
+        ));
+      }
+    }
+
+    let mut dep_path = path.clone();
+    dep_path.push(dep_ref.clone());
+    dependencies.push(dep_ref);
+    visit_template(
+      manifest,
+      &(dep_type, dep_name),
+      dep_path,
+      spec.version.map(String::from),
+      spec.git_ref.map(String::from),
+      state,
+    );
+  }
+  state.stack.pop();
+
+  state.resolved.insert(template.clone());
+  state.plan.templates.push(ResolvedTemplate {
+    name: name.clone(),
+    template_type: type_name.clone(),
+    reason: format_reason(&state.stack.first().unwrap_or(template).0, &path),
+    dependency: path.len() > 1,
+    path,
+    dependencies,
+    version,
+    git_ref,
+  });
+}
+
+/**
+ * 例如 page `dashboard` → `chart` → `tooltip`
+ */
+fn format_reason(root_type: &str, path: &[String]) -> String {
+  let names: Vec<String> = path.iter().map(|name| format!("`{}`", name)).collect();
+  let label = root_type.strip_suffix('s').unwrap_or(root_type);
+
+  format!("{} {}", label, names.join(" → "))
+}
+
+/**
+ * features 为选择的功能组或可选依赖，不传时只解析必需的依赖
+ */
+pub async fn resolve_dependencies(
+  type_name: &str,
+  name: &str,
+  features: &[String],
+) -> Result<Vec<ResolvedTemplate>, String> {
+  let config = get_config();
+  let manifest = fetch_remote_yaml(&config.github_api_token, &config.target_branch).await?;
+  let templates = manifest.get_templates(type_name);
+  let template = templates.iter().find(|item| item.name == name);
+  let optional = match template {
+    Some(template) => {
+      for warning in template.get_unknown_selections(features) {
+        println!("{} {}", "Warning:".yellow().bold(), warning.yellow());
+      }
+      template.get_selected_dependencies(features)
+    }
+    None => vec![],
+  };
+  let plan = resolve_template_dependencies(&manifest, type_name, name, &optional)?;
+
+  plan.print_tree();
+  plan.print_warnings();
+  for (index, item) in plan.templates.iter().enumerate() {
+    println!(
+      "{} {} ----> {}",
+      format!("{}.", index + 1).white(),
+      format!("{}/{}", item.template_type, item.name).green(),
+      item.reason
+    );
+  }
+
+  Ok(plan.templates)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::request::schema::parse_remote_yaml;
+
+  fn parse(content: &str) -> RemoteYaml {
+    parse_remote_yaml(content).unwrap()
+  }
+
+  fn order(plan: &DependencyPlan) -> Vec<String> {
+    plan
+      .templates
+      .iter()
+      .map(|item| format_template_ref(&item.template_type, &item.name))
+      .collect()
+  }
+
+  #[test]
+  fn installs_dependencies_before_their_dependents() {
+    let manifest = parse(
+      r#"
+components:
+  - name: chart
+    description: chart
+    dependencies: [tooltip, utils/format]
+  - name: tooltip
+    description: tooltip
+    dependencies: [utils/format]
+pages:
+  - name: dashboard
+    description: dashboard
+    dependencies: [chart, tooltip]
+types:
+  - name: utils
+    templates:
+      - name: format
+        description: format
+"#,
+    );
+
+    let plan = resolve_template_dependencies(&manifest, "pages", "dashboard", &[]).unwrap();
+
+    assert_eq!(
+      order(&plan),
+      vec!["utils/format", "tooltip", "chart", "pages/dashboard"]
+    );
+    assert!(plan.warnings.is_empty());
+    assert_eq!(
+      plan.templates[0].reason,
+      "page `dashboard` → `chart` → `tooltip` → `utils/format`"
+    );
+    assert!(!plan.templates[3].dependency);
+    assert_eq!(
+      plan.get_dependencies(),
+      vec![
+        (String::from("utils"), String::from("format")),
+        (String::from("components"), String::from("tooltip")),
+        (String::from("components"), String::from("chart")),
+      ]
+    );
+  }
+
+  #[test]
+  fn warns_about_cycles_and_unknown_dependencies() {
+    let manifest = parse(
+      r#"
+components:
+  - name: a
+    description: a
+    dependencies: [b, missing]
+  - name: b
+    description: b
+    dependencies: [a]
+"#,
+    );
+
+    let plan = resolve_template_dependencies(&manifest, "components", "a", &[]).unwrap();
+
+    assert_eq!(order(&plan), vec!["b", "a"]);
+    assert_eq!(
+      plan.warnings,
+      vec![
+        "dependency cycle: `a` → `b` → `a`",
+        "component `a` depends on `missing`, which is not declared in components",
+      ]
+    );
+  }
+
+  #[test]
+  fn resolves_optional_dependencies_only_for_the_selected_template() {
+    let manifest = parse(
+      r#"
+components:
+  - name: table
+    description: table
+    optional_dependencies: [csv]
+  - name: csv
+    description: csv
+pages:
+  - name: list
+    description: list
+    dependencies: [table]
+"#,
+    );
+
+    let plan = resolve_template_dependencies(&manifest, "pages", "list", &[]).unwrap();
+    assert_eq!(order(&plan), vec!["table", "pages/list"]);
+
+    let plan =
+      resolve_template_dependencies(&manifest, "components", "table", &[String::from("csv")])
+        .unwrap();
+    assert_eq!(order(&plan), vec!["csv", "table"]);
+  }
+
+  #[test]
+  fn keeps_the_first_version_and_warns_about_conflicts() {
+    let manifest = parse(
+      r#"
+components:
+  - name: form
+    description: form
+    dependencies: [button@^1.2, input]
+  - name: input
+    description: input
+    dependencies: [button#main]
+  - name: button
+    description: button
+"#,
+    );
+
+    let plan = resolve_template_dependencies(&manifest, "components", "form", &[]).unwrap();
+    let button = &plan.templates[0];
+
+    assert_eq!(order(&plan), vec!["button", "input", "form"]);
+    assert_eq!(button.version.as_deref(), Some("^1.2"));
+    assert_eq!(button.get_spec_suffix(), "@^1.2");
+    assert_eq!(
+      plan.warnings,
+      vec!["component `form` → `input` requires `button#main`, but it is already resolved as `button@^1.2`"]
+    );
+  }
+
+  #[test]
+  fn returns_an_error_for_undeclared_templates() {
+    let manifest = parse("components: []");
+
+    assert!(resolve_template_dependencies(&manifest, "components", "button", &[]).is_err());
+  }
+
+  #[test]
+  fn parses_typed_dependencies_and_falls_back_to_components() {
+    let manifest = parse(
+      r#"
+types:
+  - name: hooks
+"#,
+    );
+
+    assert_eq!(
+      parse_dependency(&manifest, "hooks/useAuth@^1"),
+      (String::from("hooks"), String::from("useAuth"))
+    );
+    assert_eq!(
+      parse_dependency(&manifest, "pages/login#main"),
+      (String::from("pages"), String::from("login"))
+    );
+    assert_eq!(
+      parse_dependency(&manifest, "unknown/thing"),
+      (String::from("components"), String::from("unknown/thing"))
+    );
+  }
+}
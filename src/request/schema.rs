@@ -0,0 +1,198 @@
+use std::fs;
+
+use colored::Colorize;
+use schemars::{schema::RootSchema, schema_for};
+use serde_json::Value;
+
+use crate::helper::utils::get_local_dir_file_path_string;
+
+use super::RemoteYaml;
+
+pub const MANIFEST_FILE_NAME: &str = "wego.yaml";
+pub const MANIFEST_SCHEMA_FILE_NAME: &str = "wego.schema.json";
+
+/**
+ * 由 RemoteYaml 生成的 JSON Schema，编辑器可以用它校验和补全远程 wego.yaml
+ */
+pub fn get_manifest_schema() -> Result<String, String> {
+  serde_json::to_string_pretty(&build_manifest_schema()).map_err(|e| e.to_string())
+}
+
+fn build_manifest_schema() -> RootSchema {
+  let mut schema = schema_for!(RemoteYaml);
+  schema.schema.metadata().title = Some(String::from(MANIFEST_FILE_NAME));
+
+  schema
+}
+
+/**
+ * 找出 wego.yaml 中 schema 未声明的字段，返回字段路径，例如 components[2].descriptin
+ * 解析时这些字段会被忽略，由 lint 给出警告，避免拼写错误的字段悄悄失效
+ */
+pub fn find_unknown_fields(content: &str) -> Vec<String> {
+  let manifest = match serde_yaml::from_str::<serde_yaml::Value>(content) {
+    Ok(manifest) => manifest,
+    Err(_) => return vec![],
+  };
+  let schema = serde_json::to_value(build_manifest_schema()).unwrap_or_default();
+  let mut result = vec![];
+  collect_unknown_fields(&schema, &schema, &manifest, String::new(), &mut result);
+
+  result
+}
+
+fn collect_unknown_fields(
+  root: &Value,
+  schema: &Value,
+  value: &serde_yaml::Value,
+  path: String,
+  result: &mut Vec<String>,
+) {
+  if let Some(name) = schema
+    .get("$ref")
+    .and_then(Value::as_str)
+    .and_then(|item| item.strip_prefix("#/definitions/"))
+  {
+    if let Some(definition) = root.get("definitions").and_then(|item| item.get(name)) {
+      collect_unknown_fields(root, definition, value, path, result);
+    }
+    return;
+  }
+
+  // Option<T> 生成的 schema 为 anyOf 或 allOf
+  for key in ["allOf", "anyOf", "oneOf"] {
+    for item in schema
+      .get(key)
+      .and_then(Value::as_array)
+      .into_iter()
+      .flatten()
+    {
+      collect_unknown_fields(root, item, value, path.clone(), result);
+    }
+  }
+
+  match value {
+    serde_yaml::Value::Mapping(mapping) => {
+      let properties = match schema.get("properties").and_then(Value::as_object) {
+        Some(properties) => properties,
+        None => return,
+      };
+      for (key, item) in mapping {
+        let key = match key.as_str() {
+          Some(key) => key,
+          None => continue,
+        };
+        let field_path = if path.is_empty() {
+          key.to_string()
+        } else {
+          format!("{}.{}", path, key)
+        };
+        match properties.get(key) {
+          Some(property) => collect_unknown_fields(root, property, item, field_path, result),
+          None => result.push(field_path),
+        }
+      }
+    }
+    serde_yaml::Value::Sequence(sequence) => {
+      if let Some(items) = schema.get("items") {
+        for (index, item) in sequence.iter().enumerate() {
+          collect_unknown_fields(root, items, item, format!("{}[{}]", path, index), result);
+        }
+      }
+    }
+    _ => {}
+  }
+}
+
+/**
+ * 把 JSON Schema 写入 output，默认写入当前目录的 wego.schema.json，返回写入的路径
+ */
+pub fn write_manifest_schema(output: Option<String>) -> Result<String, String> {
+  let file_path =
+    output.unwrap_or_else(|| get_local_dir_file_path_string(MANIFEST_SCHEMA_FILE_NAME));
+  let content = get_manifest_schema()?;
+
+  fs::write(&file_path, format!("{}\n", content)).map_err(|e| e.to_string())?;
+  println!("{}, Write done!", file_path.green());
+
+  Ok(file_path)
+}
+
+/**
+ * 解析远程 wego.yaml，出错时返回带行号、列号和出错行内容的错误信息
+ */
+pub fn parse_remote_yaml(content: &str) -> Result<RemoteYaml, String> {
+  serde_yaml::from_str::<RemoteYaml>(content).map_err(|e| format_yaml_error(content, &e))
+}
+
+fn format_yaml_error(content: &str, error: &serde_yaml::Error) -> String {
+  let message = error.to_string();
+  let location = match error.location() {
+    Some(location) => location,
+    None => return format!("{}: {}", MANIFEST_FILE_NAME, message),
+  };
+
+  // serde_yaml 的错误信息末尾自带位置，统一放到文件名后面
+  let message = match message.rfind(" at line ") {
+    Some(index) => &message[..index],
+    None => &message,
+  };
+  let line = content
+    .lines()
+    .nth(location.line().saturating_sub(1))
+    .unwrap_or_default();
+
+  format!(
+    "{}:{}:{}: {}\n{:>5} | {}\n{:>5} | {}^",
+    MANIFEST_FILE_NAME,
+    location.line(),
+    location.column(),
+    message,
+    location.line(),
+    line,
+    "",
+    " ".repeat(location.column().saturating_sub(1))
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reports_unknown_fields_with_their_path() {
+    let content = r#"
+components:
+  - name: button
+    descriptin: typo
+    description: Button
+    features:
+      export: [csv]
+projects:
+  - name: admin
+    description: Admin
+    dependencies: [button]
+    extra: true
+types:
+  - name: hooks
+    lable: Hooks
+    templates:
+      - name: useAuth
+        description: auth
+        tag: [auth]
+homepage: https://example.com
+"#;
+
+    assert_eq!(
+      find_unknown_fields(content),
+      vec![
+        "components[0].descriptin",
+        "projects[0].extra",
+        "types[0].lable",
+        "types[0].templates[0].tag",
+        "homepage",
+      ]
+    );
+    assert!(parse_remote_yaml(content).is_ok());
+  }
+}
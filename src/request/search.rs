@@ -0,0 +1,314 @@
+use std::cmp::Reverse;
+
+use colored::Colorize;
+use dialoguer::{console::Term, theme::ColorfulTheme, FuzzySelect};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+
+use crate::GenerateOptions;
+
+use super::{
+  request::{fetch_remote_yaml, get_config, get_remote_yaml_config},
+  ConfigYaml, RemoteYaml,
+};
+
+/**
+ * 搜索模板的参数，筛选条件均不区分大小写
+ */
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+  /**
+   * 模糊匹配名称、描述、标签和分类，为空时列出所有满足筛选条件的模板
+   */
+  pub query: Option<String>,
+  pub template_type: Option<String>,
+  pub tag: Option<String>,
+  pub category: Option<String>,
+  pub author: Option<String>,
+  /**
+   * 为 true 时从搜索结果中选择一个模板并生成
+   */
+  pub select: Option<bool>,
+}
+
+/**
+ * 模板搜索结果，按匹配度从高到低排列
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TemplateSearchResult {
+  pub name: String,
+  pub template_type: String,
+  pub description: String,
+  pub tags: Vec<String>,
+  pub category: Option<String>,
+  pub author: Option<String>,
+  /**
+   * 匹配度，没有 query 时为 0
+   */
+  pub score: i64,
+}
+
+pub async fn search_templates(
+  options: &SearchOptions,
+) -> Result<Vec<TemplateSearchResult>, String> {
+  let config = get_config();
+  let manifest = fetch_remote_yaml(&config.github_api_token, &config.target_branch).await?;
+  let result = search_manifest(&manifest, options);
+
+  if result.is_empty() {
+    println!("{}", "No template matched!".yellow());
+    return Ok(result);
+  }
+
+  if options.select.unwrap_or(false) {
+    // 选择列表中不能带颜色，否则会影响模糊匹配
+    let items: Vec<String> = result
+      .iter()
+      .map(|item| {
+        format!(
+          "[{}] {} ----> {} {}",
+          item.template_type,
+          item.name,
+          item.description,
+          get_search_labels(item)
+        )
+      })
+      .collect();
+    let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+      .with_prompt("Select a template")
+      .items(&items)
+      .default(0)
+      .interact_on_opt(&Term::stderr())
+      .map_err(|e| e.to_string())?;
+
+    if let Some(index) = selection {
+      let template = &result[index];
+      let generate_options = GenerateOptions {
+        template: Some(template.name.clone()),
+        ..Default::default()
+      };
+      get_remote_yaml_config(&template.template_type, generate_options).await;
+    }
+  } else {
+    for item in &result {
+      println!(
+        "{} {} ----> {} {}",
+        format!("[{}]", item.template_type).white(),
+        item.name.green(),
+        item.description,
+        get_search_labels(item).cyan()
+      );
+    }
+  }
+
+  Ok(result)
+}
+
+/**
+ * 在所有类型的模板中搜索，先按筛选条件过滤，再按 query 的匹配度排序
+ */
+pub fn search_manifest(
+  manifest: &RemoteYaml,
+  options: &SearchOptions,
+) -> Vec<TemplateSearchResult> {
+  let matcher = SkimMatcherV2::default().ignore_case();
+  let query = options.query.as_deref().map(str::trim).unwrap_or_default();
+  let mut result = vec![];
+
+  for template_type in manifest.get_types() {
+    if !matches_filter(Some(&template_type.name), options.template_type.as_deref()) {
+      continue;
+    }
+
+    for template in &template_type.templates {
+      let tags = template.tags.clone().unwrap_or_default();
+      let matched = matches_filter(template.category.as_deref(), options.category.as_deref())
+        && matches_filter(template.author.as_deref(), options.author.as_deref())
+        && match options.tag.as_deref() {
+          Some(tag) => tags.iter().any(|item| item.eq_ignore_ascii_case(tag)),
+          None => true,
+        };
+      if !matched {
+        continue;
+      }
+
+      let score = if query.is_empty() {
+        Some(0)
+      } else {
+        match_template(&matcher, template, query)
+      };
+
+      if let Some(score) = score {
+        result.push(TemplateSearchResult {
+          name: template.name.clone(),
+          template_type: template_type.name.clone(),
+          description: template.description.clone(),
+          tags,
+          category: template.category.clone(),
+          author: template.author.clone(),
+          score,
+        });
+      }
+    }
+  }
+
+  result.sort_by_key(|item| Reverse(item.score));
+
+  result
+}
+
+fn matches_filter(value: Option<&str>, filter: Option<&str>) -> bool {
+  match filter {
+    Some(filter) => value.is_some_and(|value| value.eq_ignore_ascii_case(filter)),
+    None => true,
+  }
+}
+
+/**
+ * 名称的匹配度加倍，使名称匹配的模板排在只有描述匹配的模板前面
+ */
+fn match_template(matcher: &SkimMatcherV2, template: &ConfigYaml, query: &str) -> Option<i64> {
+  let name = matcher
+    .fuzzy_match(&template.name, query)
+    .map(|score| score * 2);
+  let others = [
+    Some(template.description.as_str()),
+    template.category.as_deref(),
+  ]
+  .into_iter()
+  .flatten()
+  .chain(template.tags.iter().flatten().map(String::as_str))
+  .filter_map(|text| matcher.fuzzy_match(text, query));
+
+  name.into_iter().chain(others).max()
+}
+
+/**
+ * 例如 [form] #input #antd @alice
+ */
+fn get_search_labels(item: &TemplateSearchResult) -> String {
+  let mut labels: Vec<String> = vec![];
+  if let Some(category) = &item.category {
+    labels.push(format!("[{}]", category));
+  }
+  labels.extend(item.tags.iter().map(|tag| format!("#{}", tag)));
+  if let Some(author) = &item.author {
+    labels.push(format!("@{}", author));
+  }
+
+  labels.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::request::schema::parse_remote_yaml;
+
+  fn manifest() -> RemoteYaml {
+    parse_remote_yaml(
+      r#"
+components:
+  - name: button
+    description: Clickable control
+    tags: [Form, antd]
+    category: general
+    author: Alice
+  - name: table
+    description: Data grid with a button toolbar
+    tags: [data]
+    category: data-display
+    author: bob
+pages:
+  - name: login
+    description: Login page
+    tags: [form]
+    author: alice
+"#,
+    )
+    .unwrap()
+  }
+
+  fn search(options: SearchOptions) -> Vec<(String, String)> {
+    search_manifest(&manifest(), &options)
+      .into_iter()
+      .map(|item| (item.template_type, item.name))
+      .collect()
+  }
+
+  fn item(template_type: &str, name: &str) -> (String, String) {
+    (template_type.to_string(), name.to_string())
+  }
+
+  #[test]
+  fn lists_all_templates_with_zero_score_without_query() {
+    let result = search_manifest(
+      &manifest(),
+      &SearchOptions {
+        query: Some(String::from("  ")),
+        ..Default::default()
+      },
+    );
+
+    assert_eq!(result.len(), 3);
+    assert!(result.iter().all(|item| item.score == 0));
+  }
+
+  #[test]
+  fn filters_case_insensitively() {
+    assert_eq!(
+      search(SearchOptions {
+        template_type: Some(String::from("Pages")),
+        ..Default::default()
+      }),
+      vec![item("pages", "login")]
+    );
+    assert_eq!(
+      search(SearchOptions {
+        tag: Some(String::from("FORM")),
+        ..Default::default()
+      }),
+      vec![item("components", "button"), item("pages", "login")]
+    );
+    assert_eq!(
+      search(SearchOptions {
+        category: Some(String::from("Data-Display")),
+        ..Default::default()
+      }),
+      vec![item("components", "table")]
+    );
+    assert_eq!(
+      search(SearchOptions {
+        author: Some(String::from("alice")),
+        tag: Some(String::from("antd")),
+        ..Default::default()
+      }),
+      vec![item("components", "button")]
+    );
+    assert!(search(SearchOptions {
+      author: Some(String::from("carol")),
+      ..Default::default()
+    })
+    .is_empty());
+  }
+
+  #[test]
+  fn ranks_name_matches_above_description_matches() {
+    let result = search_manifest(
+      &manifest(),
+      &SearchOptions {
+        query: Some(String::from("button")),
+        ..Default::default()
+      },
+    );
+
+    assert_eq!(
+      result
+        .iter()
+        .map(|item| item.name.as_str())
+        .collect::<Vec<_>>(),
+      vec!["button", "table"]
+    );
+    assert!(result[0].score > result[1].score);
+  }
+}
@@ -0,0 +1,139 @@
+use colored::Colorize;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::{
+  request::{fetch_remote_yaml, get_config},
+  ConfigYaml,
+};
+
+/**
+ * 远程 wego.yaml 中声明的模板类型
+ * 除内置的 components、pages、projects 外，可以声明 hooks、services 等任意类型
+ */
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct TypeConfig {
+  pub name: String,
+  /**
+   * 模板在 templates_source 下的目录，默认与 name 相同
+   */
+  pub source: Option<String>,
+  /**
+   * 生成到本地的默认目录，本地 wego.yaml 配置了该类型的 destinations 时不生效
+   */
+  pub destination: Option<String>,
+  /**
+   * 选择模板时的提示文字
+   */
+  pub label: Option<String>,
+  #[serde(default)]
+  pub templates: Vec<ConfigYaml>,
+}
+
+impl TypeConfig {
+  pub fn new(name: &str, templates: Vec<ConfigYaml>) -> Self {
+    TypeConfig {
+      name: name.to_string(),
+      source: None,
+      destination: None,
+      label: None,
+      templates,
+    }
+  }
+
+  pub fn get_source(&self) -> String {
+    self.source.clone().unwrap_or_else(|| self.name.clone())
+  }
+
+  pub fn get_label(&self) -> String {
+    self
+      .label
+      .clone()
+      .unwrap_or_else(|| format!("Select a template of {}", self.name))
+  }
+
+  /**
+   * 模板声明的生成目录，没有声明时使用类型的 destination
+   */
+  pub fn get_destination(&self, template: &ConfigYaml) -> Option<String> {
+    template
+      .destination
+      .clone()
+      .or_else(|| self.destination.clone())
+  }
+
+  /**
+   * 选择列表的每一项，包含分类、标签和作者，模糊搜索时可以匹配到
+   */
+  pub fn get_output_string_vec(&self) -> Vec<String> {
+    self
+      .templates
+      .iter()
+      .map(|item| {
+        let output = match &item.dependencies {
+          Some(deps) => format!("{} ----> {} ----> {:?}", item.name, item.description, deps),
+          None => format!("{} ----> {}", item.name, item.description),
+        };
+        let labels = item.get_labels();
+
+        if labels.is_empty() {
+          output
+        } else {
+          format!("{} {}", output, labels)
+        }
+      })
+      .collect()
+  }
+}
+
+/**
+ * 远程仓库中的模板类型
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct TemplateTypeInfo {
+  pub name: String,
+  pub label: String,
+  /**
+   * 模板在远程仓库 templates_source 下的目录
+   */
+  pub source: String,
+  /**
+   * 生成到本地的目录
+   */
+  pub destination: String,
+  pub count: u32,
+}
+
+pub async fn list_template_types() -> Result<Vec<TemplateTypeInfo>, String> {
+  let config = get_config();
+  let manifest = fetch_remote_yaml(&config.github_api_token, &config.target_branch).await?;
+
+  let result: Vec<TemplateTypeInfo> = manifest
+    .get_types()
+    .into_iter()
+    .map(|item| TemplateTypeInfo {
+      name: item.name.clone(),
+      label: item.get_label(),
+      source: item.get_source(),
+      destination: config.get_destination_root(&item.name, item.destination.as_deref()),
+      count: item.templates.len() as u32,
+    })
+    .collect();
+
+  for item in &result {
+    println!(
+      "{} {} ----> {} templates ----> {}",
+      format!("[{}]", item.name).white(),
+      item.label,
+      item.count.to_string().green(),
+      if item.destination.is_empty() {
+        "."
+      } else {
+        &item.destination
+      }
+    );
+  }
+
+  Ok(result)
+}
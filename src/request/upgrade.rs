@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+
+use colored::Colorize;
+
+use crate::helper::{
+  lock::{hash_contents, read_local_files, record_installed_templates, LockFile, LockedTemplate},
+  merge::merge_dir,
+  staging::StagingDir,
+  ConfigFile,
+};
+
+use super::{
+  request::{
+    fetch_remote_yaml, fetch_template_files, fetch_template_tree_shas, get_config,
+    resolve_revision, TemplateRevision,
+  },
+  version::resolve_locked_revision,
+};
+
+pub const STATUS_UP_TO_DATE: &str = "up-to-date";
+pub const STATUS_UPGRADED: &str = "upgraded";
+pub const STATUS_CONFLICT: &str = "conflict";
+pub const STATUS_REMOVED: &str = "removed";
+pub const STATUS_FAILED: &str = "failed";
+
+/**
+ * 模板升级结果
+ */
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct UpgradeResult {
+  pub name: String,
+  pub template_type: String,
+  pub path: String,
+  /**
+   * up-to-date | upgraded | conflict | removed | failed
+   */
+  pub status: String,
+  pub from_commit: String,
+  pub to_commit: String,
+  pub updated: Vec<String>,
+  pub deleted: Vec<String>,
+  pub conflicts: Vec<String>,
+  pub message: Option<String>,
+}
+
+impl UpgradeResult {
+  fn new(template: &LockedTemplate, status: &str) -> Self {
+    UpgradeResult {
+      name: template.name.clone(),
+      template_type: template.template_type.clone(),
+      path: template.path.clone(),
+      status: status.to_string(),
+      from_commit: template.commit.clone(),
+      to_commit: template.commit.clone(),
+      updated: vec![],
+      deleted: vec![],
+      conflicts: vec![],
+      message: None,
+    }
+  }
+
+  fn failed(template: &LockedTemplate, message: String) -> Self {
+    let mut result = UpgradeResult::new(template, STATUS_FAILED);
+    result.message = Some(message);
+    result
+  }
+}
+
+/**
+ * 升级已安装的模板，names 为空时升级 wego.lock 中的所有模板
+ * 以安装时的版本为 base，本地文件为 ours，远程最新版本为 theirs 做三方合并
+ */
+pub async fn upgrade_templates(names: Vec<String>) -> Vec<UpgradeResult> {
+  let lock_file = match LockFile::read_from_local() {
+    Ok(lock_file) => lock_file,
+    Err(e) => {
+      println!("{} {}", "Error:".red().bold(), e.red());
+      return vec![];
+    }
+  };
+
+  let config = get_config();
+  let repo = config.get_repo_full_name();
+  let revision = resolve_revision(&config.github_api_token, &config.target_branch).await;
+
+  if revision.commit.is_empty() {
+    return vec![];
+  }
+
+  let templates: Vec<&LockedTemplate> = lock_file
+    .templates
+    .iter()
+    .filter(|item| item.repo == repo)
+    .filter(|item| names.is_empty() || names.contains(&item.name) || names.contains(&item.path))
+    .collect();
+
+  if templates.is_empty() {
+    println!("{}", "There is no template to upgrade!".yellow());
+    return vec![];
+  }
+
+  // 有版本约束的模板需要 head 上 wego.yaml 中声明的版本
+  let manifest = if templates.iter().any(|item| !item.version_req.is_empty()) {
+    fetch_remote_yaml(&config.github_api_token, revision.fetch_ref())
+      .await
+      .ok()
+  } else {
+    None
+  };
+
+  let mut latest_trees: BTreeMap<(String, String), BTreeMap<String, String>> = BTreeMap::new();
+  let mut upgraded = vec![];
+  let mut result = vec![];
+
+  for template in templates {
+    let source = template.get_source();
+    let head_version = manifest
+      .as_ref()
+      .and_then(|manifest| manifest.get_version(&template.template_type, &template.name));
+    let template_revision = match resolve_locked_revision(
+      &config.github_api_token,
+      template,
+      &revision,
+      head_version.as_deref(),
+    )
+    .await
+    {
+      Ok(template_revision) => template_revision,
+      Err(e) => {
+        result.push(UpgradeResult::failed(template, e));
+        continue;
+      }
+    };
+    let key = (
+      source.to_string(),
+      template_revision.fetch_ref().to_string(),
+    );
+
+    if !latest_trees.contains_key(&key) {
+      match fetch_template_tree_shas(
+        source,
+        &config.github_api_token,
+        template_revision.fetch_ref(),
+      )
+      .await
+      {
+        Ok(trees) => {
+          latest_trees.insert(key.clone(), trees);
+        }
+        Err(e) => {
+          result.push(UpgradeResult::failed(template, e));
+          continue;
+        }
+      }
+    }
+
+    let latest_tree = match latest_trees[&key].get(&template.name) {
+      Some(tree) => tree.clone(),
+      None => {
+        result.push(UpgradeResult::new(template, STATUS_REMOVED));
+        continue;
+      }
+    };
+
+    if latest_tree == template.tree {
+      result.push(UpgradeResult::new(template, STATUS_UP_TO_DATE));
+      continue;
+    }
+
+    match upgrade_template(template, &config, &template_revision, latest_tree).await {
+      Ok((item, locked)) => {
+        upgraded.push(locked);
+        result.push(item);
+      }
+      Err(e) => result.push(UpgradeResult::failed(template, e)),
+    }
+  }
+
+  record_installed_templates(upgraded);
+  print_upgrade_results(&result);
+
+  result
+}
+
+async fn upgrade_template(
+  template: &LockedTemplate,
+  config: &ConfigFile,
+  revision: &TemplateRevision,
+  latest_tree: String,
+) -> Result<(UpgradeResult, LockedTemplate), String> {
+  if template.commit.is_empty() {
+    return Err(String::from(
+      "the installed commit is unknown, reinstall the template first",
+    ));
+  }
+
+  let url = config.get_remote_template_url(template.get_source(), &template.name);
+  let token = &config.github_api_token;
+
+  let (base, theirs) = futures::join!(
+    fetch_template_files(url.clone(), token, &template.commit),
+    fetch_template_files(url, token, revision.fetch_ref())
+  );
+  let (base, theirs) = (base?, theirs?);
+
+  // 合并结果先写入暂存目录，全部成功后再逐个替换模板目录中的文件，其他本地文件保持不变
+  let local_path = template.get_local_path();
+  let ours = read_local_files(&local_path, base.keys().chain(theirs.keys()));
+  let staging = StagingDir::new(&local_path)?;
+  let report = merge_dir(&staging.path_string(), &base, &ours, &theirs)?;
+  staging.commit_files(&local_path, &report.deleted)?;
+
+  let status = if report.conflicts.is_empty() {
+    STATUS_UPGRADED
+  } else {
+    STATUS_CONFLICT
+  };
+
+  let mut result = UpgradeResult::new(template, status);
+  result.to_commit = revision.commit.clone();
+  result.updated = report.updated;
+  result.deleted = report.deleted;
+  result.conflicts = report.conflicts;
+
+  let mut locked = template.clone();
+  locked.git_ref = revision.git_ref.clone();
+  locked.commit = revision.commit.clone();
+  locked.version = revision.version.clone();
+  locked.tree = latest_tree;
+  locked.files = hash_contents(&theirs);
+
+  Ok((result, locked))
+}
+
+fn print_upgrade_results(list: &[UpgradeResult]) {
+  for item in list {
+    let status = match item.status.as_str() {
+      STATUS_UP_TO_DATE => item.status.green(),
+      STATUS_UPGRADED => item.status.green().bold(),
+      STATUS_CONFLICT => item.status.yellow().bold(),
+      _ => item.status.red().bold(),
+    };
+
+    println!(
+      "{} {} ----> {}",
+      format!("[{}]", item.template_type).white(),
+      item.path,
+      status
+    );
+
+    for path in &item.updated {
+      println!("  {} {}", "M".green(), path);
+    }
+    for path in &item.deleted {
+      println!("  {} {}", "D".red(), path);
+    }
+    for conflict in &item.conflicts {
+      println!("  {} {}", "C".yellow().bold(), conflict.yellow());
+    }
+    if let Some(message) = &item.message {
+      println!("  {}", message.red());
+    }
+  }
+}
@@ -0,0 +1,325 @@
+use colored::Colorize;
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+
+use crate::helper::lock::LockedTemplate;
+
+use super::{
+  request::{
+    fetch_commit_sha, fetch_remote_yaml, get_config, request_github_api, resolve_revision,
+    TemplateRevision,
+  },
+  resolve::format_template_ref,
+};
+
+#[derive(Debug, Deserialize)]
+struct GithubRef {
+  #[serde(rename = "ref")]
+  git_ref: String,
+}
+
+/**
+ * 模板引用中的名称、版本约束和 ref，例如 button@^1.2、pages/login#main、button#a1b2c3d
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateSpec<'a> {
+  pub name: &'a str,
+  pub version: Option<&'a str>,
+  /**
+   * # 后的分支、tag 或 commit sha，tag 可以包含 @，例如 button#button@1.4.0
+   */
+  pub git_ref: Option<&'a str>,
+}
+
+impl TemplateSpec<'_> {
+  /**
+   * 名称之后的部分，例如 @^1.2、#main，都没有时为空
+   */
+  pub fn get_suffix(&self) -> String {
+    format_spec_suffix(self.version, self.git_ref)
+  }
+}
+
+pub fn format_spec_suffix(version: Option<&str>, git_ref: Option<&str>) -> String {
+  match (version, git_ref) {
+    (_, Some(git_ref)) => format!("#{}", git_ref),
+    (Some(version), None) => format!("@{}", version),
+    (None, None) => String::new(),
+  }
+}
+
+pub fn parse_template_spec(spec: &str) -> TemplateSpec<'_> {
+  let (rest, git_ref) = split_non_empty(spec, '#');
+  let (name, version) = split_non_empty(rest, '@');
+
+  TemplateSpec {
+    name,
+    version,
+    git_ref,
+  }
+}
+
+fn split_non_empty(value: &str, separator: char) -> (&str, Option<&str>) {
+  match value.split_once(separator) {
+    Some((name, rest)) if !rest.trim().is_empty() => (name, Some(rest.trim())),
+    Some((name, _)) => (name, None),
+    None => (value, None),
+  }
+}
+
+/**
+ * 解析版本约束，只写版本号时与 cargo 相同按 ^ 处理，例如 1.2 等同于 ^1.2
+ */
+pub fn parse_version_req(req: &str) -> Result<VersionReq, String> {
+  VersionReq::parse(req).map_err(|e| format!("invalid version constraint {}: {}", req, e))
+}
+
+/**
+ * 解析版本号，允许带 v 前缀，例如 v1.4.0
+ */
+pub fn parse_version(version: &str) -> Result<Version, String> {
+  let version = version.trim();
+
+  Version::parse(version.strip_prefix('v').unwrap_or(version))
+    .map_err(|e| format!("invalid version {}: {}", version, e))
+}
+
+/**
+ * 模板版本 tag 的前缀，components 为 button@，其他类型为 pages/login@
+ */
+fn get_tag_prefix(type_name: &str, name: &str) -> String {
+  format!("{}@", format_template_ref(type_name, name))
+}
+
+/**
+ * 远程仓库中模板的所有版本 tag，按版本从高到低排列，无法解析版本的 tag 会被忽略
+ */
+pub async fn fetch_template_tags(
+  token: &str,
+  type_name: &str,
+  name: &str,
+) -> Result<Vec<(Version, String)>, String> {
+  let prefix = get_tag_prefix(type_name, name);
+  let uri = get_config().get_remote_matching_tags_url(&prefix);
+  let body = request_github_api(&uri, token, None).await?;
+  let refs = serde_json::from_slice::<Vec<GithubRef>>(&body).map_err(|e| e.to_string())?;
+
+  let mut tags: Vec<(Version, String)> = refs
+    .into_iter()
+    .filter_map(|item| {
+      let tag = item.git_ref.strip_prefix("refs/tags/")?;
+      let version = parse_version(tag.strip_prefix(&prefix)?).ok()?;
+      Some((version, tag.to_string()))
+    })
+    .collect();
+  tags.sort_by(|a, b| b.0.cmp(&a.0));
+
+  Ok(tags)
+}
+
+/**
+ * 把版本约束解析为具体的提交，候选为模板的版本 tag 以及 head 上 wego.yaml 中声明的 version
+ * 选择满足约束的最高版本，同一版本同时有 tag 和 version 声明时使用 tag
+ */
+pub async fn resolve_template_version(
+  token: &str,
+  type_name: &str,
+  name: &str,
+  req: &str,
+  head: &TemplateRevision,
+  head_version: Option<&str>,
+) -> Result<TemplateRevision, String> {
+  let version_req = parse_version_req(req)?;
+  let tagged = fetch_template_tags(token, type_name, name)
+    .await?
+    .into_iter()
+    .find(|(version, _)| version_req.matches(version));
+  let declared = head_version
+    .and_then(|version| parse_version(version).ok())
+    .filter(|version| version_req.matches(version));
+
+  match (tagged, declared) {
+    (Some((version, tag)), declared) if !matches!(&declared, Some(item) if item > &version) => {
+      let commit = fetch_commit_sha(token, &tag).await?;
+      Ok(TemplateRevision {
+        git_ref: tag,
+        commit,
+        version: version.to_string(),
+        version_req: req.to_string(),
+        pinned: false,
+      })
+    }
+    (_, Some(version)) => Ok(TemplateRevision {
+      version: version.to_string(),
+      version_req: req.to_string(),
+      ..head.clone()
+    }),
+    _ => Err(format!(
+      "no version of {} matches {}",
+      format_template_ref(type_name, name),
+      req
+    )),
+  }
+}
+
+/**
+ * 已安装模板对比和升级时使用的版本，安装时指定了 ref 则使用该 ref 的最新提交，
+ * 指定了版本约束则在约束范围内选择最新版本，否则为 head
+ */
+pub async fn resolve_locked_revision(
+  token: &str,
+  template: &LockedTemplate,
+  head: &TemplateRevision,
+  head_version: Option<&str>,
+) -> Result<TemplateRevision, String> {
+  if template.pinned {
+    return Ok(resolve_pinned_revision(token, &template.git_ref).await);
+  }
+  if template.version_req.is_empty() {
+    return Ok(head.clone());
+  }
+
+  resolve_template_version(
+    token,
+    &template.template_type,
+    &template.name,
+    &template.version_req,
+    head,
+    head_version,
+  )
+  .await
+}
+
+/**
+ * 已安装模板升级时会选择的版本，head 为 target_branch 的最新提交
+ */
+pub async fn resolve_upgrade_revision(
+  template: &LockedTemplate,
+) -> Result<TemplateRevision, String> {
+  let config = get_config();
+  let head = resolve_revision(&config.github_api_token, &config.target_branch).await;
+  let head_version = if template.version_req.is_empty() {
+    None
+  } else {
+    fetch_remote_yaml(&config.github_api_token, head.fetch_ref())
+      .await
+      .ok()
+      .and_then(|manifest| manifest.get_version(&template.template_type, &template.name))
+  };
+
+  resolve_locked_revision(
+    &config.github_api_token,
+    template,
+    &head,
+    head_version.as_deref(),
+  )
+  .await
+}
+
+pub fn format_resolved_version(type_name: &str, name: &str, revision: &TemplateRevision) -> String {
+  format!(
+    "{} {}@{} ----> {} ({})",
+    "Resolved".green(),
+    format_template_ref(type_name, name),
+    revision.version_req,
+    revision.version.green(),
+    revision.fetch_ref()
+  )
+}
+
+/**
+ * 通过分支、tag 或 commit sha 指定的版本，记录到 wego.lock 后升级时继续使用该 ref
+ */
+pub async fn resolve_pinned_revision(token: &str, git_ref: &str) -> TemplateRevision {
+  TemplateRevision {
+    pinned: true,
+    ..resolve_revision(token, git_ref).await
+  }
+}
+
+/**
+ * 按模板引用中的 ref 或版本约束解析出使用的版本，都没有时使用 head，ref 优先
+ */
+pub async fn resolve_spec_revision(
+  token: &str,
+  type_name: &str,
+  name: &str,
+  version: Option<&str>,
+  git_ref: Option<&str>,
+  head: &TemplateRevision,
+  head_version: Option<&str>,
+) -> Result<TemplateRevision, String> {
+  match (git_ref, version) {
+    (Some(git_ref), _) => Ok(resolve_pinned_revision(token, git_ref).await),
+    (None, Some(req)) => {
+      resolve_template_version(token, type_name, name, req, head, head_version).await
+    }
+    (None, None) => Ok(head.clone()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(spec: &str) -> (&str, Option<&str>, Option<&str>) {
+    let spec = parse_template_spec(spec);
+
+    (spec.name, spec.version, spec.git_ref)
+  }
+
+  #[test]
+  fn parses_versions_and_refs_in_template_specs() {
+    assert_eq!(parse("button"), ("button", None, None));
+    assert_eq!(parse("button@^1.2"), ("button", Some("^1.2"), None));
+    assert_eq!(
+      parse("pages/login#main"),
+      ("pages/login", None, Some("main"))
+    );
+    assert_eq!(
+      parse("button@^1.2#a1b2c3d"),
+      ("button", Some("^1.2"), Some("a1b2c3d"))
+    );
+    assert_eq!(
+      parse("button#button@1.4.0"),
+      ("button", None, Some("button@1.4.0"))
+    );
+  }
+
+  #[test]
+  fn ignores_empty_versions_and_refs() {
+    assert_eq!(parse("button@"), ("button", None, None));
+    assert_eq!(parse("button#"), ("button", None, None));
+    assert_eq!(parse("button@ #main"), ("button", None, Some("main")));
+  }
+
+  #[test]
+  fn prefers_the_ref_in_spec_suffixes() {
+    assert_eq!(parse_template_spec("button").get_suffix(), "");
+    assert_eq!(parse_template_spec("button@^1.2").get_suffix(), "@^1.2");
+    assert_eq!(
+      parse_template_spec("button@^1.2#main").get_suffix(),
+      "#main"
+    );
+    assert_eq!(format_spec_suffix(Some("1.4.0"), None), "@1.4.0");
+  }
+
+  #[test]
+  fn parses_versions_with_a_v_prefix() {
+    assert_eq!(parse_version("v1.4.0").unwrap(), Version::new(1, 4, 0));
+    assert_eq!(parse_version(" 1.4.0 ").unwrap(), Version::new(1, 4, 0));
+    assert!(parse_version("1.4").is_err());
+  }
+
+  #[test]
+  fn treats_bare_versions_as_caret_requirements() {
+    let req = parse_version_req("1.2").unwrap();
+
+    assert!(req.matches(&Version::new(1, 9, 0)));
+    assert!(!req.matches(&Version::new(2, 0, 0)));
+    assert!(!parse_version_req("~1.2")
+      .unwrap()
+      .matches(&Version::new(1, 3, 0)));
+    assert!(parse_version_req("latest").is_err());
+  }
+}